@@ -0,0 +1,53 @@
+use screen_overlay::widgets::{Countdown, CountdownStyle, Indicator};
+use screen_overlay::{Color, Error, Overlay, OverlayConfig, Point, Rect, TextProperties};
+use std::time::{Duration, Instant};
+
+pub fn main() -> std::result::Result<(), Error> {
+    screen_overlay::setup()?;
+    let window = Overlay::new_with_config(&OverlayConfig {
+        name: "Countdown".to_owned(),
+        ..Default::default()
+    })?;
+
+    let font = window.prepare_font(&TextProperties {
+        size: 48.0,
+        ..Default::default()
+    })?;
+    let style = CountdownStyle {
+        layout: Rect::from(100.0, 100.0).sized(300.0, 60.0),
+        format: "mm:ss.t".to_owned(),
+        thresholds: vec![
+            (Duration::from_secs(10), Color::rgb(255, 200, 0)),
+            (Duration::from_secs(3), Color::rgb(255, 0, 0)),
+        ],
+        indicator: Some(Indicator::Radial {
+            center: Point::new(60.0, 130.0),
+            radius: 25.0,
+            thickness: 8.0,
+            background: Color::rgba(0, 0, 0, 128),
+        }),
+        ..Default::default()
+    };
+    let countdown = Countdown::new(
+        &window,
+        Instant::now() + Duration::from_secs(20),
+        &font,
+        &style,
+    )?;
+    // Show a message for a few seconds, the overlay removes it after fading it out.
+    let message_font = font.clone();
+    countdown.on_complete(move |overlay| {
+        let layout = Rect::from(100.0, 200.0).sized(300.0, 60.0);
+        let shown = overlay
+            .draw_text("time is up", &layout, &Color::WHITE, &message_font)
+            .and_then(|token| {
+                token.expire_after_with_fade(Duration::from_secs(3), Duration::from_secs(1))
+            });
+        if let Err(e) = shown {
+            eprintln!("failed to show message: {e}");
+        }
+    });
+
+    screen_overlay::block_and_loop()?;
+    Ok(())
+}
@@ -3,15 +3,19 @@ use screen_overlay::{
     Color,
     DashStyle,
     DrawGeometry,
+    DrawTextureOptions,
+    Easing,
     Error,
     LineStyle,
     Overlay,
     OverlayConfig,
     Point,
+    Property,
     Rect,
     Stroke,
     TextAlignment,
     TextProperties,
+    Tween,
 };
 
 pub fn main() -> std::result::Result<(), Error> {
@@ -32,7 +36,7 @@ pub fn main() -> std::result::Result<(), Error> {
         };
         let alpha = 0.5;
         let image2 = twindow
-            .load_texture(std::path::PathBuf::from(
+            .load_texture_cached(std::path::PathBuf::from(
                 "PNG_transparency_demonstration_1.png",
             ))
             .expect("failed to load image");
@@ -47,7 +51,7 @@ pub fn main() -> std::result::Result<(), Error> {
             .expect("texture draw failed");
 
         let image = twindow
-            .load_texture(std::path::PathBuf::from("image.jpg"))
+            .load_texture_cached(std::path::PathBuf::from("image.jpg"))
             .expect("failed to load image");
         let _t = twindow
             .draw_texture(
@@ -58,7 +62,34 @@ pub fn main() -> std::result::Result<(), Error> {
                 alpha,
             )
             .expect("texture draw failed");
+        let _t3 = twindow
+            .draw_texture_with_options(
+                &image,
+                &DrawTextureOptions {
+                    region: Some(Rect::from(0.0, 0.0).sized(200.0, 200.0)),
+                    rotation: 30.0,
+                    flip_horizontal: true,
+                    tint: Color::rgb(128, 255, 128),
+                    ..DrawTextureOptions::new(Rect::from(1000.0, 750.0).sized(100.0, 100.0))
+                },
+            )
+            .expect("texture draw failed");
+        // Pulse the rotated image, the overlay runs the tween in the background.
+        let _pulse = _t3
+            .animate(&Tween {
+                loops: None,
+                yoyo: true,
+                ..Tween::new(
+                    Property::Opacity,
+                    1.0,
+                    0.2,
+                    std::time::Duration::from_millis(800),
+                    Easing::InOutSine,
+                )
+            })
+            .expect("animating failed");
 
+        let mut text_box = None;
         for i in 0..50000 {
             let pos = Rect::from(200.0 + 50.0 * (i % 5) as f32, 200.0).sized(200.0, 300.0);
             let geometry = DrawGeometry::new().rectangle(&pos);
@@ -75,10 +106,6 @@ pub fn main() -> std::result::Result<(), Error> {
                 ..Default::default()
             };
 
-            let _v = twindow
-                .draw_geometry(&geometry, &stroke, &text_box_style)
-                .expect("create image failed");
-
             let font = twindow
                 .prepare_font(&TextProperties {
                     size: 32.0,
@@ -94,9 +121,16 @@ pub fn main() -> std::result::Result<(), Error> {
                 b: 255,
                 a: 128,
             };
-            let _v = twindow
-                .draw_text("hello there we are rendering text", &pos, &color, &font)
-                .expect("create image failed");
+            twindow
+                .transaction(|tx| {
+                    let frame = tx.draw_geometry(&geometry, &stroke, &text_box_style)?;
+                    let text =
+                        tx.draw_text("hello there we are rendering text", &pos, &color, &font)?;
+                    // Replacing the previous box and text inside the transaction swaps them without flicker.
+                    text_box = Some((frame, text));
+                    Ok(())
+                })
+                .expect("drawing the text box failed");
 
             std::thread::sleep(std::time::Duration::from_millis(1000));
         }
@@ -137,7 +171,6 @@ pub fn main() -> std::result::Result<(), Error> {
         println!("blocking now");
         std::thread::sleep(std::time::Duration::from_millis(1000000));
     });
-    Ok(screen_overlay::block_and_loop()?)
-
-    // Ok(())
+    screen_overlay::block_and_loop()?;
+    Ok(())
 }
@@ -0,0 +1,110 @@
+use screen_overlay::widgets::{
+    Gauge, GaugeStyle, Graph, GraphStyle, Label, Orientation, ProgressBar, ProgressBarStyle,
+    SeriesStyle, StatsLabel,
+};
+use screen_overlay::{Color, Error, Overlay, OverlayConfig, Point, Rect, Stroke, TextProperties};
+use std::time::Duration;
+
+pub fn main() -> std::result::Result<(), Error> {
+    screen_overlay::setup()?;
+    let window = Overlay::new_with_config(&OverlayConfig {
+        name: "Meters".to_owned(),
+        ..Default::default()
+    })?;
+
+    let twindow = window.clone();
+    let _update_thread = std::thread::spawn(move || -> std::result::Result<(), Error> {
+        let font = twindow.prepare_font(&TextProperties {
+            size: 20.0,
+            ..Default::default()
+        })?;
+        let thresholds = vec![
+            (0.6, Color::rgb(255, 200, 0)),
+            (0.85, Color::rgb(255, 0, 0)),
+        ];
+        let bar = ProgressBar::new(
+            &twindow,
+            &ProgressBarStyle {
+                area: Rect::from(100.0, 100.0).sized(300.0, 20.0),
+                thresholds: thresholds.clone(),
+                label: Some(Label {
+                    layout: Rect::from(410.0, 95.0).sized(80.0, 30.0),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Some(&font),
+        )?;
+        let segmented = ProgressBar::new(
+            &twindow,
+            &ProgressBarStyle {
+                area: Rect::from(100.0, 150.0).sized(20.0, 150.0),
+                orientation: Orientation::Vertical,
+                segments: 10,
+                color: Color::rgb(0, 200, 255),
+                ..Default::default()
+            },
+            None,
+        )?;
+        let gauge = Gauge::new(
+            &twindow,
+            &GaugeStyle {
+                center: Point::new(250.0, 250.0),
+                radius: 60.0,
+                range: (0.0, 200.0),
+                thresholds: vec![
+                    (120.0, Color::rgb(255, 200, 0)),
+                    (170.0, Color::rgb(255, 0, 0)),
+                ],
+                label: Some(Label {
+                    layout: Rect::from(210.0, 235.0).sized(80.0, 30.0),
+                    text: "{value} km/h".to_owned(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Some(&font),
+        )?;
+
+        let graph = Graph::new(
+            &twindow,
+            &GraphStyle {
+                area: Rect::from(350.0, 200.0).sized(300.0, 100.0),
+                series: vec![
+                    SeriesStyle {
+                        fill: Color::rgba(0, 200, 255, 64),
+                        ..Default::default()
+                    },
+                    SeriesStyle {
+                        stroke: Stroke {
+                            color: Color::rgb(255, 200, 0),
+                            width: 1.5,
+                        },
+                        ..Default::default()
+                    },
+                ],
+                stats: Some(StatsLabel {
+                    layout: Rect::from(350.0, 305.0).sized(300.0, 22.0),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+            Some(&font),
+        )?;
+
+        let mut t = 0.0f32;
+        loop {
+            std::thread::sleep(Duration::from_millis(16));
+            t += 0.016;
+            let wave = (t.sin() + 1.0) / 2.0;
+            bar.set_value(wave)?;
+            segmented.set_value(1.0 - wave)?;
+            gauge.set_value(wave * 200.0)?;
+            graph.push(wave * 100.0)?;
+            graph.push_series(1, (t * 3.1).sin() * 20.0 + 50.0)?;
+        }
+    });
+
+    screen_overlay::block_and_loop()?;
+    Ok(())
+}
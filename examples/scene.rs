@@ -0,0 +1,33 @@
+use screen_overlay::scene::SceneFormat;
+use screen_overlay::watch::SceneWatcher;
+use screen_overlay::{Error, Overlay, OverlayConfig, Point};
+
+pub fn main() -> std::result::Result<(), Error> {
+    screen_overlay::setup()?;
+    let window = Overlay::new_with_config(&OverlayConfig {
+        name: "Scene".to_owned(),
+        ..Default::default()
+    })?;
+
+    // Edit the scene file while this runs to see the changes.
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or("examples/scene.toml".to_owned());
+    let watcher = SceneWatcher::new(&window, &path)?;
+
+    // Move the title and print the scene as it is now.
+    watcher.with_scene(|scene| -> Result<(), Error> {
+        println!(
+            "loaded {path} with ids {:?}",
+            scene.ids().collect::<Vec<_>>()
+        );
+        if let Some(title) = scene.get("title") {
+            title.set_position(&Point::new(50.0, 0.0))?;
+        }
+        println!("{}", scene.snapshot().to_string(SceneFormat::Ron)?);
+        Ok(())
+    })?;
+
+    screen_overlay::block_and_loop()?;
+    Ok(())
+}
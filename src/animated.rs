@@ -0,0 +1,415 @@
+//! Animated images, decoded into one texture per frame and played back by the overlay's scheduler.
+
+use crate::scheduler::TaskId;
+use crate::{
+    DrawTextureOptions, Error, Group, ImageFormat, ImageTexture, Overlay, PixelFormat, VisualToken,
+};
+
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Browsers show frames with a delay of 10ms or less for 100ms, many gifs rely on this.
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+/// Browsers also don't go faster than this.
+const MINIMUM_DELAY: Duration = Duration::from_millis(20);
+
+/// Decoded frames in straight alpha RGBA with their display duration.
+pub(crate) struct DecodedFrame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub delay: Duration,
+}
+
+/// Decode all frames of an animated GIF, PNG or WebP, still images result in a single frame.
+pub(crate) fn decode_frames(data: &[u8], format: ImageFormat) -> Result<Vec<DecodedFrame>, Error> {
+    use image::codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder};
+    use image::AnimationDecoder;
+    let cursor = std::io::Cursor::new(data);
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(cursor)?.into_frames().collect_frames()?,
+        ImageFormat::Png => {
+            let decoder = PngDecoder::new(cursor)?;
+            if decoder.is_apng()? {
+                decoder.apng()?.into_frames().collect_frames()?
+            } else {
+                vec![]
+            }
+        }
+        ImageFormat::WebP => {
+            let decoder = WebPDecoder::new(cursor)?;
+            if decoder.has_animation() {
+                decoder.into_frames().collect_frames()?
+            } else {
+                vec![]
+            }
+        }
+        _ => vec![],
+    };
+    if frames.is_empty() {
+        let image = image::load_from_memory(data)?.into_rgba8();
+        return Ok(vec![DecodedFrame {
+            width: image.width(),
+            height: image.height(),
+            pixels: image.into_raw(),
+            delay: DEFAULT_DELAY,
+        }]);
+    }
+    Ok(frames
+        .into_iter()
+        .map(|frame| {
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            let delay =
+                Duration::from_secs_f64(numerator as f64 / denominator.max(1) as f64 / 1000.0);
+            let image = frame.into_buffer();
+            DecodedFrame {
+                width: image.width(),
+                height: image.height(),
+                pixels: image.into_raw(),
+                delay: if delay <= Duration::from_millis(10) {
+                    DEFAULT_DELAY
+                } else {
+                    delay.max(MINIMUM_DELAY)
+                },
+            }
+        })
+        .collect())
+}
+
+/// The frames of an animated image, cheap to clone.
+#[derive(Clone, Debug)]
+pub struct AnimatedTexture {
+    frames: Arc<Vec<(ImageTexture, Duration)>>,
+}
+
+impl AnimatedTexture {
+    pub(crate) fn new(frames: Vec<(ImageTexture, Duration)>) -> Result<Self, Error> {
+        if frames.is_empty() {
+            return Err("animation has no frames".into());
+        }
+        Ok(Self {
+            frames: Arc::new(frames),
+        })
+    }
+
+    pub(crate) fn create(overlay: &Overlay, decoded: Vec<DecodedFrame>) -> Result<Self, Error> {
+        let frames = decoded
+            .into_iter()
+            .map(|f| {
+                let texture =
+                    overlay.create_texture(f.width, f.height, &f.pixels, PixelFormat::Rgba8)?;
+                Ok((texture, f.delay))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Self::new(frames)
+    }
+
+    /// The textures of the frames and how long each is shown.
+    pub fn frames(&self) -> &[(ImageTexture, Duration)] {
+        &self.frames
+    }
+
+    /// The duration of one pass through all frames.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|(_, d)| *d).sum()
+    }
+}
+
+/// The frame index shown at `elapsed` and when that frame ends, `None` for the end if it is the last frame of the
+/// last loop. A `loops` of `None` repeats forever.
+fn frame_at(
+    delays: impl Iterator<Item = Duration> + Clone,
+    elapsed: Duration,
+    loops: Option<u32>,
+) -> (usize, Option<Duration>) {
+    let total: Duration = delays.clone().sum();
+    let last = delays.clone().count().saturating_sub(1);
+    if total.is_zero() {
+        return (last, None);
+    }
+    let pass = (elapsed.as_nanos() / total.as_nanos()) as u64;
+    if loops.is_some_and(|l| pass >= l.max(1) as u64) {
+        return (last, None);
+    }
+    let mut end = total * pass as u32;
+    for (index, delay) in delays.enumerate() {
+        end += delay;
+        if elapsed < end {
+            let final_frame = index == last && loops.is_some_and(|l| pass + 1 >= l as u64);
+            return (index, (!final_frame).then_some(end));
+        }
+    }
+    (last, None)
+}
+
+/// A frame as it is played back, the texture is drawn with the options for the duration.
+pub(crate) type PlaybackFrame = (ImageTexture, DrawTextureOptions, Duration);
+
+struct PlaybackState {
+    frames: Arc<Vec<PlaybackFrame>>,
+    loops: Option<u32>,
+    /// Playback position at `anchor`.
+    position: Duration,
+    /// The time `position` was taken, `None` while paused.
+    anchor: Option<Instant>,
+    frame: Option<usize>,
+    /// Holds the visual of the current frame.
+    group: Group,
+    visual: Option<VisualToken>,
+    task: Option<TaskId>,
+    /// Changed on every restart, so a task that was already running when it was cancelled leaves `task` alone.
+    generation: u64,
+}
+
+impl PlaybackState {
+    fn frame_at(&self, elapsed: Duration) -> (usize, Option<Duration>) {
+        frame_at(self.frames.iter().map(|(_, _, d)| *d), elapsed, self.loops)
+    }
+
+    fn elapsed(&self, now: Instant) -> Duration {
+        self.position
+            + self
+                .anchor
+                .map(|a| now.saturating_duration_since(a))
+                .unwrap_or_default()
+    }
+
+    /// Show the frame for `now`, returns when the next frame is due if it is playing.
+    fn update(&mut self, overlay: &Overlay, now: Instant) -> Result<Option<Instant>, Error> {
+        let elapsed = self.elapsed(now);
+        let (frame, end) = self.frame_at(elapsed);
+        if self.frame != Some(frame) {
+            let (texture, options, _) = &self.frames[frame];
+            // Draw the new frame before removing the old one, that way there's no flicker.
+            let visual = overlay.draw_texture_with_options(texture, options)?;
+            self.group.add(&visual)?;
+            self.visual = Some(visual);
+            self.frame = Some(frame);
+        }
+        Ok(match (self.anchor, end) {
+            (Some(_), Some(end)) => Some(now + (end - elapsed)),
+            _ => None,
+        })
+    }
+}
+
+/// A drawn animation, it advances by itself until it is dropped, which removes it from the overlay.
+///
+/// The frames are drawn in the animation's [`Group`], that is where it is positioned, hidden or animated.
+pub struct AnimationToken {
+    overlay: Overlay,
+    group: Group,
+    /// Only locked while holding the overlay's lock, drawing a frame locks the overlay.
+    state: Arc<Mutex<PlaybackState>>,
+}
+
+impl std::fmt::Debug for AnimationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        let _guard = self.overlay.overlay.lock();
+        let state = self.state.lock();
+        write!(
+            f,
+            "AnimationToken {:?} frame {:?}",
+            state.visual, state.frame
+        )
+    }
+}
+
+impl AnimationToken {
+    pub(crate) fn new(
+        overlay: &Overlay,
+        animation: &AnimatedTexture,
+        options: &DrawTextureOptions,
+    ) -> Result<AnimationToken, Error> {
+        let frames = animation
+            .frames
+            .iter()
+            .map(|(texture, delay)| (texture.clone(), *options, *delay))
+            .collect();
+        Self::from_frames(overlay, frames)
+    }
+
+    /// Play frames that each have their own options, like the regions of an atlas.
+    pub(crate) fn from_frames(
+        overlay: &Overlay,
+        frames: Vec<PlaybackFrame>,
+    ) -> Result<AnimationToken, Error> {
+        if frames.is_empty() {
+            return Err("animation has no frames".into());
+        }
+        let group = overlay.create_group()?;
+        let token = AnimationToken {
+            overlay: overlay.clone(),
+            group: group.clone(),
+            state: Arc::new(Mutex::new(PlaybackState {
+                frames: Arc::new(frames),
+                loops: None,
+                position: Duration::ZERO,
+                anchor: Some(Instant::now()),
+                frame: None,
+                group,
+                visual: None,
+                task: None,
+                generation: 0,
+            })),
+        };
+        token.change(|_| {})?;
+        Ok(token)
+    }
+
+    /// Change the playback, then redraw for the current position and schedule the next frame if playing.
+    fn change<F: FnOnce(&mut PlaybackState)>(&self, f: F) -> Result<(), Error> {
+        self.overlay.transaction(|overlay| {
+            let mut state = self.state.lock();
+            f(&mut state);
+            if let Some(task) = state.task.take() {
+                overlay.scheduler.cancel(task);
+            }
+            state.generation += 1;
+            let generation = state.generation;
+            if let Some(due) = state.update(overlay, Instant::now())? {
+                let weak = Arc::downgrade(&self.state);
+                let task = overlay.scheduler.schedule(
+                    due,
+                    Box::new(move |overlay, now| {
+                        let state = weak.upgrade()?;
+                        overlay
+                            .transaction(|overlay| {
+                                let mut state = state.lock();
+                                if state.generation != generation {
+                                    return Ok(None);
+                                }
+                                let next = state.update(overlay, now).ok().flatten();
+                                if next.is_none() {
+                                    state.task = None;
+                                }
+                                Ok(next)
+                            })
+                            .ok()
+                            .flatten()
+                    }),
+                );
+                state.task = Some(task);
+            }
+            Ok(())
+        })
+    }
+
+    /// The group holding the animation's frames.
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    /// Number of times the animation plays before stopping on the last frame, `None` repeats forever.
+    pub fn set_loop_count(&self, loops: Option<u32>) -> Result<(), Error> {
+        self.change(|state| state.loops = loops)
+    }
+
+    pub fn play(&self) -> Result<(), Error> {
+        self.change(|state| {
+            if state.anchor.is_none() {
+                state.anchor = Some(Instant::now());
+            }
+        })
+    }
+
+    /// Stop advancing, the current frame stays visible.
+    pub fn pause(&self) -> Result<(), Error> {
+        self.change(|state| {
+            state.position = state.elapsed(Instant::now());
+            state.anchor = None;
+        })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        let _guard = self.overlay.overlay.lock();
+        self.state.lock().anchor.is_some()
+    }
+
+    /// Jump to a position from the start of the first loop.
+    pub fn seek(&self, position: Duration) -> Result<(), Error> {
+        self.change(|state| {
+            state.position = position;
+            if state.anchor.is_some() {
+                state.anchor = Some(Instant::now());
+            }
+        })
+    }
+
+    /// The position from the start of the first loop.
+    pub fn position(&self) -> Duration {
+        let _guard = self.overlay.overlay.lock();
+        self.state.lock().elapsed(Instant::now())
+    }
+
+    /// True once the last loop has been played.
+    pub fn is_finished(&self) -> bool {
+        let _guard = self.overlay.overlay.lock();
+        let state = self.state.lock();
+        let elapsed = state.elapsed(Instant::now());
+        state.loops.is_some() && state.frame_at(elapsed).1.is_none()
+    }
+}
+
+impl Drop for AnimationToken {
+    fn drop(&mut self) {
+        let _ = self.overlay.transaction(|overlay| {
+            let mut state = self.state.lock();
+            if let Some(task) = state.task.take() {
+                overlay.scheduler.cancel(task);
+            }
+            state.visual = None;
+            Ok(())
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(delays: &[u64]) -> Vec<Duration> {
+        delays.iter().map(|d| Duration::from_millis(*d)).collect()
+    }
+
+    #[test]
+    fn test_decode_gif_frames() {
+        use image::codecs::gif::GifEncoder;
+        use image::{Delay, Frame, RgbaImage};
+        let mut data = vec![];
+        {
+            let mut encoder = GifEncoder::new(&mut data);
+            let frames = [([255, 0, 0, 255], 50), ([0, 255, 0, 255], 5)].map(|(color, ms)| {
+                Frame::from_parts(
+                    RgbaImage::from_pixel(2, 2, image::Rgba(color)),
+                    0,
+                    0,
+                    Delay::from_numer_denom_ms(ms, 1),
+                )
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+        let frames = decode_frames(&data, ImageFormat::detect(&data).unwrap()).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].delay, Duration::from_millis(50));
+        // Tiny delays are shown the way browsers show them.
+        assert_eq!(frames[1].delay, DEFAULT_DELAY);
+        assert_eq!((frames[1].width, frames[1].height), (2, 2));
+        assert_eq!(&frames[1].pixels[0..4], &[0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn test_frame_timing() {
+        let delays = timing(&[100, 50, 100]);
+        let frame_at = |elapsed, loops| frame_at(delays.iter().copied(), elapsed, loops);
+        let ms = Duration::from_millis;
+        assert_eq!(frame_at(ms(0), None), (0, Some(ms(100))));
+        assert_eq!(frame_at(ms(120), None), (1, Some(ms(150))));
+        assert_eq!(frame_at(ms(260), None), (0, Some(ms(350))));
+        // The last frame of the last loop has no end.
+        assert_eq!(frame_at(ms(160), Some(1)), (2, None));
+        assert_eq!(frame_at(ms(900), Some(2)), (2, None));
+        assert_eq!(frame_at(ms(260), Some(2)), (0, Some(ms(350))));
+    }
+}
@@ -0,0 +1,504 @@
+//! Texture atlases, many named images packed into a single texture.
+//!
+//! The descriptor is the JSON written by TexturePacker and Aseprite, both the 'hash' and 'array' layouts are
+//! supported. Frame names have their image extension removed, so `skull.png` is drawn as `skull`.
+
+use crate::{
+    AnimationToken, DrawTextureOptions, Error, ImageTexture, Overlay, Point, Rect, VisualToken,
+};
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Frame duration used for sequences when the descriptor doesn't specify one.
+const DEFAULT_FRAME_DURATION: Duration = Duration::from_millis(100);
+
+/// A single image inside the atlas.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AtlasFrame {
+    /// The area of the texture holding the frame, for rotated frames this is the rotated area.
+    pub region: Rect,
+    /// The frame is stored rotated 90 degrees clockwise in the texture.
+    pub rotated: bool,
+    /// Offset of the packed pixels in the original image, non zero if transparent borders were trimmed.
+    pub offset: Point,
+    /// Width and height of the original image, before trimming.
+    pub size: (f32, f32),
+    pub duration: Option<Duration>,
+}
+
+impl AtlasFrame {
+    /// The options to draw this frame with its original top left at `position`, the region is the frame's.
+    pub fn draw_options(&self, position: &Point) -> DrawTextureOptions {
+        let top_left = *position + self.offset;
+        let (w, h) = (self.region.width(), self.region.height());
+        let options = if self.rotated {
+            // The packed pixels are rotated clockwise, draw the region counter clockwise about the target's center.
+            let target = Rect::from(top_left.x, top_left.y).sized(h, w);
+            DrawTextureOptions {
+                rotation: -90.0,
+                ..DrawTextureOptions::new(Rect::from_center_size(&target.center(), w, h))
+            }
+        } else {
+            DrawTextureOptions::new(Rect::from(top_left.x, top_left.y).sized(w, h))
+        };
+        DrawTextureOptions {
+            region: Some(self.region),
+            ..options
+        }
+    }
+}
+
+/// A sequence of atlas frames played back one after the other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasAnimation {
+    pub frames: Vec<(String, Duration)>,
+}
+
+impl AtlasAnimation {
+    /// The total duration of one pass through the frames.
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|(_, d)| *d).sum()
+    }
+
+    /// The name of the frame shown at `elapsed`, looping around at the end.
+    pub fn frame_at(&self, elapsed: Duration) -> Option<&str> {
+        let total = self.duration().as_nanos();
+        if total == 0 {
+            return self.frames.first().map(|(n, _)| n.as_str());
+        }
+        let mut remaining = elapsed.as_nanos() % total;
+        for (name, duration) in self.frames.iter() {
+            if remaining < duration.as_nanos() {
+                return Some(name);
+            }
+            remaining -= duration.as_nanos();
+        }
+        None
+    }
+}
+
+/// A texture with named regions, see the module documentation for the descriptor format.
+#[derive(Clone, Debug)]
+pub struct Atlas {
+    texture: ImageTexture,
+    frames: HashMap<String, AtlasFrame>,
+    animations: HashMap<String, AtlasAnimation>,
+}
+
+impl Atlas {
+    /// Load the descriptor and the image it references, the image path is relative to the descriptor.
+    pub fn load<P: AsRef<std::path::Path>>(
+        overlay: &Overlay,
+        descriptor: P,
+    ) -> Result<Atlas, Error> {
+        let descriptor = descriptor.as_ref();
+        let json = std::fs::read_to_string(descriptor)?;
+        let parsed: Descriptor = serde_json::from_str(&json)?;
+        let image = parsed
+            .meta
+            .image
+            .as_ref()
+            .ok_or("atlas descriptor does not reference an image")?;
+        let path = descriptor
+            .parent()
+            .map(|p| p.join(image))
+            .unwrap_or(image.into());
+        let texture = if path.extension().is_some_and(|e| e == "svg") {
+            let (w, h) = parsed.meta.size.as_ref().map(|s| (s.w, s.h)).ok_or(
+                "atlas descriptors referencing svg images need the texture size in meta.size",
+            )?;
+            overlay.load_svg(&path, (w, h))?
+        } else {
+            overlay.load_texture(&path)?
+        };
+        Self::from_descriptor(texture, parsed)
+    }
+
+    /// Create an atlas from an already loaded texture and the JSON descriptor's contents.
+    pub fn from_json(texture: ImageTexture, json: &str) -> Result<Atlas, Error> {
+        Self::from_descriptor(texture, serde_json::from_str(json)?)
+    }
+
+    fn from_descriptor(texture: ImageTexture, descriptor: Descriptor) -> Result<Atlas, Error> {
+        let (frames, animations) = parse_descriptor(descriptor)?;
+        Ok(Atlas {
+            texture,
+            frames,
+            animations,
+        })
+    }
+
+    pub fn texture(&self) -> &ImageTexture {
+        &self.texture
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&AtlasFrame> {
+        self.frames.get(name)
+    }
+
+    /// The names of all frames, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.frames.keys().map(|k| k.as_str())
+    }
+
+    /// A frame sequence by name.
+    ///
+    /// This is either a tag from the descriptor, or otherwise all frames named `name` followed by a number, like
+    /// `walk_0`, `walk_1`, ... `walk_12`, in numerical order.
+    pub fn animation(&self, name: &str) -> Option<AtlasAnimation> {
+        if let Some(animation) = self.animations.get(name) {
+            return Some(animation.clone());
+        }
+        numbered_sequence(&self.frames, name)
+    }
+
+    /// Draw a frame at its original size with its top left at `position`.
+    pub fn draw(
+        &self,
+        overlay: &Overlay,
+        name: &str,
+        position: &Point,
+    ) -> Result<VisualToken, Error> {
+        let frame = self
+            .frame(name)
+            .ok_or_else(|| format!("atlas has no frame named '{name}'"))?;
+        overlay.draw_texture_with_options(&self.texture, &frame.draw_options(position))
+    }
+
+    /// Play an animation by name with the original top left of its frames at `position`, see [`Atlas::animation`].
+    ///
+    /// It repeats until the returned token is dropped, the token controls playback like those of
+    /// [`Overlay::draw_animation`].
+    pub fn play(
+        &self,
+        overlay: &Overlay,
+        name: &str,
+        position: &Point,
+    ) -> Result<AnimationToken, Error> {
+        let animation = self
+            .animation(name)
+            .ok_or_else(|| format!("atlas has no animation named '{name}'"))?;
+        let frames = animation
+            .frames
+            .iter()
+            .map(|(frame, duration)| {
+                let frame = self
+                    .frame(frame)
+                    .ok_or_else(|| format!("atlas has no frame named '{frame}'"))?;
+                Ok((
+                    self.texture.clone(),
+                    frame.draw_options(position),
+                    *duration,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        AnimationToken::from_frames(overlay, frames)
+    }
+
+    /// Draw a frame with the provided options, the `region` is replaced by the frame's region.
+    ///
+    /// Trimmed and rotated frames are not corrected for here, use [`AtlasFrame::draw_options`] as a starting point
+    /// for those.
+    pub fn draw_with_options(
+        &self,
+        overlay: &Overlay,
+        name: &str,
+        options: &DrawTextureOptions,
+    ) -> Result<VisualToken, Error> {
+        let frame = self
+            .frame(name)
+            .ok_or_else(|| format!("atlas has no frame named '{name}'"))?;
+        let options = DrawTextureOptions {
+            region: Some(frame.region),
+            ..*options
+        };
+        overlay.draw_texture_with_options(&self.texture, &options)
+    }
+}
+
+type ParsedAtlas = (HashMap<String, AtlasFrame>, HashMap<String, AtlasAnimation>);
+
+fn parse_descriptor(descriptor: Descriptor) -> Result<ParsedAtlas, Error> {
+    // The descriptor order is kept, aseprite's tags refer to frames by index.
+    let entries: Vec<(String, FrameEntry)> = match descriptor.frames {
+        Frames::Hash(OrderedFrames(list)) => list,
+        Frames::Array(list) => list.into_iter().map(|f| (f.filename, f.entry)).collect(),
+    };
+
+    let mut order = vec![];
+    let mut frames = HashMap::new();
+    for (name, entry) in entries {
+        let r = entry.frame;
+        let (w, h) = if entry.rotated {
+            (r.h, r.w)
+        } else {
+            (r.w, r.h)
+        };
+        let frame = AtlasFrame {
+            region: Rect::from(r.x as f32, r.y as f32).sized(w as f32, h as f32),
+            rotated: entry.rotated,
+            offset: entry
+                .sprite_source_size
+                .map(|s| Point::new(s.x as f32, s.y as f32))
+                .unwrap_or(Point::ORIGIN),
+            size: entry
+                .source_size
+                .map(|s| (s.w as f32, s.h as f32))
+                .unwrap_or((r.w as f32, r.h as f32)),
+            duration: entry.duration.map(Duration::from_millis),
+        };
+        let name = strip_extension(&name).to_owned();
+        order.push(name.clone());
+        frames.insert(name, frame);
+    }
+
+    let mut animations = HashMap::new();
+    for tag in descriptor.meta.frame_tags {
+        let names = order
+            .get(tag.from..=tag.to)
+            .ok_or_else(|| format!("frame tag '{}' is out of range", tag.name))?;
+        let mut sequence: Vec<(String, Duration)> = names
+            .iter()
+            .map(|n| (n.clone(), frame_duration(&frames[n])))
+            .collect();
+        match tag.direction.as_deref() {
+            Some("reverse") => sequence.reverse(),
+            Some("pingpong") if sequence.len() > 2 => {
+                // Back down without repeating either end, the loop returns to the first frame.
+                let back: Vec<_> = sequence[1..sequence.len() - 1]
+                    .iter()
+                    .rev()
+                    .cloned()
+                    .collect();
+                sequence.extend(back);
+            }
+            _ => {}
+        }
+        animations.insert(tag.name, AtlasAnimation { frames: sequence });
+    }
+    Ok((frames, animations))
+}
+
+/// All frames named `name` followed by a number, in numerical order.
+fn numbered_sequence(frames: &HashMap<String, AtlasFrame>, name: &str) -> Option<AtlasAnimation> {
+    let mut numbered: Vec<(u64, &String)> = frames
+        .keys()
+        .filter_map(|k| {
+            let suffix = k.strip_prefix(name)?;
+            let digits = suffix.trim_start_matches(['_', '-', ' ']);
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            Some((digits.parse().ok()?, k))
+        })
+        .collect();
+    if numbered.is_empty() {
+        return None;
+    }
+    numbered.sort();
+    Some(AtlasAnimation {
+        frames: numbered
+            .into_iter()
+            .map(|(_, n)| (n.clone(), frame_duration(&frames[n])))
+            .collect(),
+    })
+}
+
+fn frame_duration(frame: &AtlasFrame) -> Duration {
+    frame.duration.unwrap_or(DEFAULT_FRAME_DURATION)
+}
+
+fn strip_extension(name: &str) -> &str {
+    for ext in [".png", ".jpg", ".jpeg", ".bmp", ".gif", ".webp", ".svg"] {
+        if name.len() > ext.len() && name.to_ascii_lowercase().ends_with(ext) {
+            return &name[..name.len() - ext.len()];
+        }
+    }
+    name
+}
+
+#[derive(Deserialize)]
+struct Descriptor {
+    frames: Frames,
+    #[serde(default)]
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Frames {
+    Hash(OrderedFrames),
+    Array(Vec<NamedFrameEntry>),
+}
+
+/// The frames of the 'hash' layout, in the order they appear in the file.
+struct OrderedFrames(Vec<(String, FrameEntry)>);
+
+impl<'de> Deserialize<'de> for OrderedFrames {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = OrderedFrames;
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map of frame names to frames")
+            }
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut frames = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    frames.push(entry);
+                }
+                Ok(OrderedFrames(frames))
+            }
+        }
+        deserializer.deserialize_map(Visitor)
+    }
+}
+
+#[derive(Deserialize)]
+struct NamedFrameEntry {
+    filename: String,
+    #[serde(flatten)]
+    entry: FrameEntry,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameEntry {
+    frame: PackedRect,
+    #[serde(default)]
+    rotated: bool,
+    sprite_source_size: Option<PackedRect>,
+    source_size: Option<PackedSize>,
+    /// Milliseconds, written by aseprite.
+    duration: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct PackedRect {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize)]
+struct PackedSize {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Meta {
+    image: Option<String>,
+    size: Option<PackedSize>,
+    #[serde(default)]
+    frame_tags: Vec<FrameTag>,
+}
+
+#[derive(Deserialize)]
+struct FrameTag {
+    name: String,
+    from: usize,
+    to: usize,
+    direction: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hash_layout() {
+        let json = r#"{
+            "frames": {
+                "skull.png": {"frame": {"x": 0, "y": 0, "w": 16, "h": 16}},
+                "walk_10.png": {"frame": {"x": 16, "y": 0, "w": 8, "h": 16}, "duration": 50},
+                "walk_2.png": {"frame": {"x": 24, "y": 0, "w": 8, "h": 16}},
+                "arrow.png": {
+                    "frame": {"x": 32, "y": 0, "w": 10, "h": 20},
+                    "rotated": true,
+                    "spriteSourceSize": {"x": 2, "y": 3, "w": 10, "h": 20},
+                    "sourceSize": {"w": 16, "h": 24}
+                }
+            },
+            "meta": {"image": "icons.png"}
+        }"#;
+        let (frames, animations) = parse_descriptor(serde_json::from_str(json).unwrap()).unwrap();
+        assert!(animations.is_empty());
+        assert_eq!(
+            frames["skull"].region,
+            Rect::from(0.0, 0.0).sized(16.0, 16.0)
+        );
+
+        let arrow = frames["arrow"];
+        assert!(arrow.rotated);
+        assert_eq!(arrow.region, Rect::from(32.0, 0.0).sized(20.0, 10.0));
+        assert_eq!(arrow.offset, Point::new(2.0, 3.0));
+        assert_eq!(arrow.size, (16.0, 24.0));
+        // Drawn rotated back, the visible result covers the unrotated frame at the trimmed offset.
+        let options = arrow.draw_options(&Point::new(100.0, 100.0));
+        let bounds = arrow
+            .region
+            .transformed_bounds(&options.transform(&arrow.region));
+        assert!((bounds.min - Point::new(102.0, 103.0)).length() < 1e-3);
+        assert!((bounds.max - Point::new(112.0, 123.0)).length() < 1e-3);
+
+        let walk = numbered_sequence(&frames, "walk").unwrap();
+        assert_eq!(
+            walk.frames,
+            vec![
+                ("walk_2".to_owned(), DEFAULT_FRAME_DURATION),
+                ("walk_10".to_owned(), Duration::from_millis(50)),
+            ]
+        );
+        assert_eq!(walk.frame_at(Duration::from_millis(120)), Some("walk_10"));
+        assert_eq!(walk.frame_at(Duration::from_millis(160)), Some("walk_2"));
+        assert!(numbered_sequence(&frames, "skull").is_none());
+    }
+
+    #[test]
+    fn test_parse_array_layout_with_tags() {
+        let json = r#"{
+            "frames": [
+                {"filename": "blink 0.aseprite", "frame": {"x": 0, "y": 0, "w": 8, "h": 8}, "duration": 100},
+                {"filename": "blink 1.aseprite", "frame": {"x": 8, "y": 0, "w": 8, "h": 8}, "duration": 100},
+                {"filename": "blink 2.aseprite", "frame": {"x": 16, "y": 0, "w": 8, "h": 8}, "duration": 200},
+                {"filename": "blink 3.aseprite", "frame": {"x": 24, "y": 0, "w": 8, "h": 8}, "duration": 100}
+            ],
+            "meta": {"frameTags": [
+                {"name": "open", "from": 0, "to": 3, "direction": "pingpong"},
+                {"name": "close", "from": 1, "to": 2, "direction": "reverse"}
+            ]}
+        }"#;
+        let (frames, animations) = parse_descriptor(serde_json::from_str(json).unwrap()).unwrap();
+        assert_eq!(frames.len(), 4);
+        let names =
+            |a: &AtlasAnimation| a.frames.iter().map(|(n, _)| n.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            names(&animations["open"]),
+            [
+                "blink 0.aseprite",
+                "blink 1.aseprite",
+                "blink 2.aseprite",
+                "blink 3.aseprite",
+                "blink 2.aseprite",
+                "blink 1.aseprite"
+            ]
+        );
+        assert_eq!(
+            names(&animations["close"]),
+            ["blink 2.aseprite", "blink 1.aseprite"]
+        );
+        assert_eq!(animations["open"].duration(), Duration::from_millis(800));
+
+        let out_of_range =
+            r#"{"frames": [], "meta": {"frameTags": [{"name": "x", "from": 0, "to": 1}]}}"#;
+        assert!(parse_descriptor(serde_json::from_str(out_of_range).unwrap()).is_err());
+    }
+}
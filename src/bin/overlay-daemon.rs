@@ -0,0 +1,33 @@
+//! Draws on the overlay for other processes, see the `server` module for the protocol.
+//!
+//! Usage: `overlay-daemon [address]`, the address is a socket path on Unix and a pipe name on Windows.
+
+use screen_overlay::server::{default_address, Server};
+use screen_overlay::{Error, Overlay, OverlayConfig};
+use std::path::PathBuf;
+
+pub fn main() -> std::result::Result<(), Error> {
+    let address = std::env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(default_address);
+
+    screen_overlay::setup()?;
+    let window = Overlay::new_with_config(&OverlayConfig {
+        task_bar: false,
+        on_top: true,
+        name: "Overlay daemon".to_owned(),
+    })?;
+    let listener = Server::new(&window).bind(&address)?;
+    println!("listening on {}", address.display());
+
+    std::thread::spawn(move || {
+        if let Err(e) = listener.run() {
+            eprintln!("stopped accepting clients: {e}");
+            std::process::exit(1);
+        }
+    });
+
+    screen_overlay::block_and_loop()?;
+    Ok(())
+}
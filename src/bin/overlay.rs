@@ -0,0 +1,257 @@
+//! Shows text or a shape on the overlay from the command line, for example as a notification from a shell script.
+//!
+//! ```text
+//! overlay text "Build done" --pos 100,100 --color #0f0 --timeout 5s
+//! overlay rect --pos 100,100 --size 200,50 --color red --fill --timeout 2s
+//! overlay circle --pos 300,300 --radius 40 --width 3
+//! ```
+//! Without `--timeout` it keeps running, end the process to remove the overlay. Shapes are geometry, which is only
+//! drawn on Windows, elsewhere `rect` and `circle` fail.
+
+use screen_overlay::{
+    Color, DrawGeometry, Error, GeometryElement, LineStyle, Overlay, OverlayConfig, Point, Rect,
+    Stroke, TextAlignment, TextProperties, VisualToken,
+};
+use std::time::Duration;
+
+const USAGE: &str = "\
+Usage: overlay <text TEXT | rect | circle> [options]
+
+Options:
+    --pos X,Y          Top left corner of the text or rectangle, center of the circle [default: 100,100]
+    --size W,H         Size of the rectangle, or the area the text is laid out in
+    --radius R         Radius of the circle [default: 50]
+    --color COLOR      Hex notation or a css color name [default: white]
+    --font NAME        Font family of the text
+    --font-size N      Size of the text [default: 32]
+    --width N          Line width of the shapes [default: 2]
+    --fill             Fill the shape instead of only drawing its outline
+    --timeout DURATION Exit after this long, like 5s, 500ms or 2m [default: never]
+    -h, --help         Show this message
+";
+
+#[derive(Clone, Debug, PartialEq)]
+enum Shape {
+    Text(String),
+    Rect,
+    Circle,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    shape: Shape,
+    position: Point,
+    size: Option<(f32, f32)>,
+    radius: f32,
+    color: Color,
+    font: TextProperties,
+    width: f32,
+    fill: bool,
+    timeout: Option<Duration>,
+}
+
+fn parse_number(value: &str) -> Result<f32, Error> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("{value:?} is not a number").into())
+}
+
+fn parse_pair(value: &str) -> Result<(f32, f32), Error> {
+    let (a, b) = value
+        .split_once(',')
+        .ok_or_else(|| format!("{value:?} should be two numbers like 100,100"))?;
+    Ok((parse_number(a)?, parse_number(b)?))
+}
+
+/// A duration with an `ms`, `s` or `m` unit, seconds if there is none.
+fn parse_duration(value: &str) -> Result<Duration, Error> {
+    let (number, scale) = if let Some(ms) = value.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = value.strip_suffix('s') {
+        (s, 1.0)
+    } else if let Some(m) = value.strip_suffix('m') {
+        (m, 60.0)
+    } else {
+        (value, 1.0)
+    };
+    let seconds: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("{value:?} is not a duration like 5s, 500ms or 2m"))?;
+    Ok(Duration::try_from_secs_f64(seconds * scale)?)
+}
+
+/// Parse the arguments after the program name, `None` if the usage was asked for.
+fn parse_args<I: Iterator<Item = String>>(mut args: I) -> Result<Option<Options>, Error> {
+    let shape = match args.next().as_deref() {
+        Some("text") => Shape::Text(args.next().ok_or("text needs the text to show")?),
+        Some("rect") => Shape::Rect,
+        Some("circle") => Shape::Circle,
+        Some("-h" | "--help") => return Ok(None),
+        Some(other) => return Err(format!("unknown shape {other:?}").into()),
+        None => return Err("missing the shape to show".into()),
+    };
+    let mut options = Options {
+        shape,
+        position: Point::new(100.0, 100.0),
+        size: None,
+        radius: 50.0,
+        color: Color::WHITE,
+        font: TextProperties {
+            size: 32.0,
+            horizontal_align: TextAlignment::Min,
+            vertical_align: TextAlignment::Min,
+            ..Default::default()
+        },
+        width: 2.0,
+        fill: false,
+        timeout: None,
+    };
+    while let Some(flag) = args.next() {
+        if flag == "-h" || flag == "--help" {
+            return Ok(None);
+        }
+        if flag == "--fill" {
+            options.fill = true;
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("{flag} needs a value"))?;
+        match flag.as_str() {
+            "--pos" => {
+                let (x, y) = parse_pair(&value)?;
+                options.position = Point::new(x, y);
+            }
+            "--size" => options.size = Some(parse_pair(&value)?),
+            "--radius" => options.radius = parse_number(&value)?,
+            "--color" => options.color = value.parse()?,
+            "--font" => options.font.font = value,
+            "--font-size" => options.font.size = parse_number(&value)?,
+            "--width" => options.width = parse_number(&value)?,
+            "--timeout" => options.timeout = Some(parse_duration(&value)?),
+            _ => return Err(format!("unknown option {flag}").into()),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn draw(overlay: &Overlay, options: &Options) -> Result<VisualToken, Error> {
+    let Point { x, y } = options.position;
+    let mut geometry = match &options.shape {
+        Shape::Text(text) => {
+            // Text isn't wrapped on every platform, so the default area is wide enough for a line of text.
+            let (w, h) = options.size.unwrap_or((2000.0, options.font.size * 1.5));
+            let font = overlay.prepare_font(&options.font)?;
+            let layout = Rect::from(x, y).sized(w, h);
+            return overlay.draw_text(text, &layout, &options.color, &font);
+        }
+        Shape::Rect | Shape::Circle if !overlay.draws_geometry() => {
+            return Err("rect and circle aren't drawn on this platform yet, only text is".into());
+        }
+        Shape::Rect => {
+            let (w, h) = options.size.unwrap_or((200.0, 100.0));
+            DrawGeometry::new().rectangle(&Rect::from(x, y).sized(w, h))
+        }
+        Shape::Circle => DrawGeometry::new().circle(&options.position, options.radius),
+    };
+    if options.fill {
+        if let Some(GeometryElement::Start { filled, .. }) = geometry.elements.first_mut() {
+            *filled = true;
+        }
+    }
+    let stroke = Stroke {
+        color: options.color,
+        width: options.width,
+    };
+    overlay.draw_geometry(&geometry, &stroke, &LineStyle::default())
+}
+
+pub fn main() -> std::result::Result<(), Error> {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            print!("{USAGE}");
+            return Ok(());
+        }
+        Err(e) => {
+            eprint!("error: {e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    screen_overlay::setup()?;
+    let window = Overlay::new_with_config(&OverlayConfig {
+        task_bar: false,
+        on_top: true,
+        name: "Overlay".to_owned(),
+    })?;
+    let _token = match draw(&window, &options) {
+        Ok(token) => token,
+        Err(e) => {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(timeout) = options.timeout {
+        std::thread::spawn(move || {
+            std::thread::sleep(timeout);
+            std::process::exit(0);
+        });
+    }
+
+    screen_overlay::block_and_loop()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Options>, Error> {
+        parse_args(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&[
+            "text",
+            "Build done",
+            "--pos",
+            "10,20.5",
+            "--color",
+            "#0f0",
+            "--timeout",
+            "5s",
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.shape, Shape::Text("Build done".to_owned()));
+        assert_eq!(options.position, Point::new(10.0, 20.5));
+        assert_eq!(options.color, Color::rgb(0, 255, 0));
+        assert_eq!(options.timeout, Some(Duration::from_secs(5)));
+
+        let options = parse(&["circle", "--fill", "--radius", "3"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(options.shape, Shape::Circle);
+        assert!(options.fill);
+        assert_eq!(options.radius, 3.0);
+
+        assert!(parse(&["rect", "--help"]).unwrap().is_none());
+        assert!(parse(&["text"]).is_err());
+        assert!(parse(&["rect", "--pos", "1"]).is_err());
+        assert!(parse(&["rect", "--width"]).is_err());
+        assert!(parse(&["rect", "--bogus", "1"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+        assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_duration("3").unwrap(), Duration::from_secs(3));
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("soon").is_err());
+    }
+}
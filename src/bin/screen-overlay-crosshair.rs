@@ -0,0 +1,39 @@
+//! Shows a crosshair from a TOML or JSON profile and redraws it whenever the profile is saved.
+//!
+//! Usage: `screen-overlay-crosshair [profile.toml]`, without a profile the default crosshair is shown.
+
+use screen_overlay::crosshair::{Crosshair, CrosshairSettings};
+use screen_overlay::watch::FileWatcher;
+use screen_overlay::{Error, Overlay, OverlayConfig};
+use std::path::PathBuf;
+
+pub fn main() -> std::result::Result<(), Error> {
+    let profile = std::env::args_os().nth(1).map(PathBuf::from);
+    let settings = match &profile {
+        Some(path) => CrosshairSettings::load(path)?,
+        None => CrosshairSettings::default(),
+    };
+
+    screen_overlay::setup()?;
+    let window = Overlay::new_with_config(&OverlayConfig {
+        task_bar: true,
+        on_top: true,
+        name: "Crosshair".to_owned(),
+    })?;
+    let crosshair = Crosshair::new(&window, &settings)?;
+
+    // Keep the current crosshair while the profile is broken, it is likely still being edited.
+    let _watcher = match profile {
+        Some(path) => Some(FileWatcher::new(path.clone(), move || {
+            let result = CrosshairSettings::load(&path).and_then(|s| crosshair.set_settings(&s));
+            match result {
+                Ok(()) => println!("reloaded {}", path.display()),
+                Err(e) => eprintln!("failed to reload {}: {e}", path.display()),
+            }
+        })?),
+        None => None,
+    };
+
+    screen_overlay::block_and_loop()?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+//! An overlay wide cache of loaded textures, so the same image is only decoded once while it is in use.
+//!
+//! The cache only holds weak references, a texture is freed as soon as the last [`ImageTexture`] handle to it is
+//! dropped and its entry is pruned the next time the cache is accessed.
+
+use crate::backend::WeakImageTexture;
+use crate::{Error, ImageTexture};
+
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Identifies a cached texture.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TextureKey {
+    /// Loaded from this file, the path is canonicalized if possible.
+    Path(PathBuf),
+    /// Loaded from memory, a hash and the length of the encoded data.
+    Content(u64, usize),
+    /// A caller chosen name, for textures created from pixels.
+    Named(String),
+}
+
+impl TextureKey {
+    pub fn path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        TextureKey::Path(std::fs::canonicalize(path).unwrap_or_else(|_| path.to_owned()))
+    }
+
+    pub fn content(data: &[u8]) -> Self {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        data.hash(&mut hasher);
+        TextureKey::Content(hasher.finish(), data.len())
+    }
+}
+
+#[derive(Default)]
+pub struct TextureCache {
+    entries: Mutex<HashMap<TextureKey, WeakImageTexture>>,
+}
+
+impl std::fmt::Debug for TextureCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "TextureCache {} entries", self.entries.lock().len())
+    }
+}
+
+impl TextureCache {
+    /// Return the cached texture, or create it with `load` and cache it.
+    ///
+    /// The cache isn't locked while loading, `load` takes the overlay's lock. Concurrent requests for the same key
+    /// may both load, the texture cached first is returned to both.
+    pub fn get_or_load<F>(&self, key: TextureKey, load: F) -> Result<ImageTexture, Error>
+    where
+        F: FnOnce() -> Result<ImageTexture, Error>,
+    {
+        {
+            let mut entries = self.entries.lock();
+            entries.retain(|_, weak| weak.upgrade().is_some());
+            if let Some(texture) = entries.get(&key).and_then(|w| w.upgrade()) {
+                return Ok(texture);
+            }
+        }
+        let texture = load()?;
+        let mut entries = self.entries.lock();
+        if let Some(cached) = entries.get(&key).and_then(|w| w.upgrade()) {
+            return Ok(cached);
+        }
+        entries.insert(key, texture.downgrade());
+        Ok(texture)
+    }
+
+    pub fn get(&self, key: &TextureKey) -> Option<ImageTexture> {
+        self.entries.lock().get(key).and_then(|w| w.upgrade())
+    }
+
+    /// Add a texture, replacing any texture cached under the same key.
+    pub fn insert(&self, key: TextureKey, texture: &ImageTexture) {
+        self.entries.lock().insert(key, texture.downgrade());
+    }
+
+    /// Remove an entry, the next load with this key creates a new texture.
+    ///
+    /// Handles to the texture that are still held stay valid. Returns true if the entry was present.
+    pub fn evict(&self, key: &TextureKey) -> bool {
+        self.entries.lock().remove(key).is_some()
+    }
+
+    /// Remove all entries.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// The keys of all textures that are still alive.
+    pub fn keys(&self) -> Vec<TextureKey> {
+        let mut entries = self.entries.lock();
+        entries.retain(|_, weak| weak.upgrade().is_some());
+        entries.keys().cloned().collect()
+    }
+
+    /// The number of textures that are still alive.
+    pub fn len(&self) -> usize {
+        self.keys().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes of pixel data held by the cached textures that are still alive, at four bytes per pixel.
+    pub fn memory_usage(&self) -> usize {
+        self.entries
+            .lock()
+            .values()
+            .filter_map(|w| w.upgrade())
+            .map(|t| t.width() as usize * t.height() as usize * 4)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_texture_key() {
+        assert_eq!(TextureKey::content(b"abc"), TextureKey::content(b"abc"));
+        assert_ne!(TextureKey::content(b"abc"), TextureKey::content(b"abd"));
+        // Relative and absolute paths to the same file share an entry.
+        let absolute = std::env::current_dir().unwrap().join("Cargo.toml");
+        assert_eq!(TextureKey::path("Cargo.toml"), TextureKey::path(&absolute));
+        assert_eq!(
+            TextureKey::path("does/not/exist.png"),
+            TextureKey::Path("does/not/exist.png".into())
+        );
+    }
+}
@@ -0,0 +1,535 @@
+use crate::Error;
+
+use serde::{Deserialize, Serialize};
+
+/// Color representation with alpha.
+///
+/// Deserializes from the struct with its four fields, a hex string like `"#00ff0080"` or a css color name.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ColorRepr")]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    /// Alpha channel, 255 is opaque, 0 is transparent.
+    pub a: u8,
+}
+
+/// The representations accepted during deserialization.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ColorRepr {
+    Str(String),
+    Rgba { r: u8, g: u8, b: u8, a: u8 },
+}
+impl TryFrom<ColorRepr> for Color {
+    type Error = Error;
+    fn try_from(repr: ColorRepr) -> Result<Self, Self::Error> {
+        match repr {
+            ColorRepr::Str(s) => s.parse(),
+            ColorRepr::Rgba { r, g, b, a } => Ok(Color { r, g, b, a }),
+        }
+    }
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    /// An opaque color.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b, a: 255 }
+    }
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
+    }
+
+    pub fn transparent(&self) -> Self {
+        Color {
+            r: self.r,
+            g: self.g,
+            b: self.b,
+            a: 0,
+        }
+    }
+    pub fn with_alpha(&self, a: u8) -> Self {
+        Color { a, ..*self }
+    }
+    pub fn a_f32(&self) -> f32 {
+        self.a as f32 / 255.0
+    }
+    pub fn r_f32(&self) -> f32 {
+        self.r as f32 / 255.0
+    }
+    pub fn g_f32(&self) -> f32 {
+        self.g as f32 / 255.0
+    }
+    pub fn b_f32(&self) -> f32 {
+        self.b as f32 / 255.0
+    }
+
+    /// Straight, not premultiplied, channels in `[0, 1]` in rgba order, as used by Direct2D.
+    pub fn to_rgba_f32(&self) -> [f32; 4] {
+        [self.r_f32(), self.g_f32(), self.b_f32(), self.a_f32()]
+    }
+
+    /// Premultiplied 16 bit channels in rgba order, as used by XRender.
+    ///
+    /// Full intensity maps to `0xffff`, channels are scaled before rounding to avoid losing precision.
+    pub fn to_premultiplied_u16(&self) -> [u16; 4] {
+        let a = self.a as u32;
+        let mul = |v: u8| ((v as u32 * a * 0xffff + 255 * 255 / 2) / (255 * 255)) as u16;
+        [mul(self.r), mul(self.g), mul(self.b), (a * 0x101) as u16]
+    }
+
+    /// Parse `#RGB`, `#RGBA`, `#RRGGBB` or `#RRGGBBAA`, the `#` is optional.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || -> Error { format!("invalid hex color {hex:?}").into() };
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let width = match digits.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return Err(invalid()),
+        };
+        let channel = |i: usize| {
+            let v = u8::from_str_radix(&digits[i * width..(i + 1) * width], 16).unwrap();
+            // A single digit is repeated, #f00 is #ff0000.
+            if width == 1 {
+                v * 17
+            } else {
+                v
+            }
+        };
+        let has_alpha = digits.len() / width == 4;
+        Ok(Color {
+            r: channel(0),
+            g: channel(1),
+            b: channel(2),
+            a: if has_alpha { channel(3) } else { 255 },
+        })
+    }
+
+    /// Format as `#rrggbbaa`.
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+
+    /// Look up a css color name, case insensitive. This includes `transparent`.
+    pub fn named(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name == "transparent" {
+            return Some(Color::TRANSPARENT);
+        }
+        NAMED_COLORS
+            .binary_search_by(|(n, _)| (*n).cmp(name.as_str()))
+            .ok()
+            .map(|i| {
+                let v = NAMED_COLORS[i].1;
+                Color::rgb((v >> 16) as u8, (v >> 8) as u8, v as u8)
+            })
+    }
+
+    /// Opaque color from the hue in degrees, saturation and value in `[0, 1]`.
+    pub fn from_hsv(h: f32, s: f32, v: f32) -> Self {
+        let c = v * s;
+        Self::from_hue_chroma(h, c, v - c)
+    }
+
+    /// Hue in degrees, saturation and value in `[0, 1]`, alpha is ignored.
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let s = if max > 0.0 { (max - min) / max } else { 0.0 };
+        (h, s, max)
+    }
+
+    /// Opaque color from the hue in degrees, saturation and lightness in `[0, 1]`.
+    pub fn from_hsl(h: f32, s: f32, l: f32) -> Self {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_hue_chroma(h, c, l - c / 2.0)
+    }
+
+    /// Hue in degrees, saturation and lightness in `[0, 1]`, alpha is ignored.
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (h, max, min) = self.hue_max_min();
+        let l = (max + min) / 2.0;
+        let s = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - (2.0 * l - 1.0).abs())
+        };
+        (h, s, l)
+    }
+
+    fn from_hue_chroma(h: f32, c: f32, m: f32) -> Self {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        let to_u8 = |v: f32| ((v + m).clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::rgb(to_u8(r), to_u8(g), to_u8(b))
+    }
+
+    fn hue_max_min(&self) -> (f32, f32, f32) {
+        let (r, g, b) = (self.r_f32(), self.g_f32(), self.b_f32());
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let d = max - min;
+        let h = if d == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / d).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / d + 2.0)
+        } else {
+            60.0 * ((r - g) / d + 4.0)
+        };
+        (h, max, min)
+    }
+
+    /// Linear interpolation of all channels, `t` of 0.0 returns `self`, 1.0 returns `other`.
+    pub fn lerp(&self, other: &Color, t: f32) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Color {
+            r: mix(self.r, other.r),
+            g: mix(self.g, other.g),
+            b: mix(self.b, other.b),
+            a: mix(self.a, other.a),
+        }
+    }
+
+    /// Channel wise product, multiplying by white changes nothing. Used to apply tints.
+    pub fn multiply(&self, other: &Color) -> Self {
+        let mul = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+        Color {
+            r: mul(self.r, other.r),
+            g: mul(self.g, other.g),
+            b: mul(self.b, other.b),
+            a: mul(self.a, other.a),
+        }
+    }
+
+    /// The color channels multiplied by alpha.
+    pub fn premultiplied(&self) -> Self {
+        let mul = |v: u8| ((v as u32 * self.a as u32 + 127) / 255) as u8;
+        Color {
+            r: mul(self.r),
+            g: mul(self.g),
+            b: mul(self.b),
+            a: self.a,
+        }
+    }
+
+    /// The inverse of [`Color::premultiplied`], fully transparent colors become [`Color::TRANSPARENT`].
+    pub fn unpremultiplied(&self) -> Self {
+        if self.a == 0 {
+            return Color::TRANSPARENT;
+        }
+        let div = |v: u8| ((v as u32 * 255 + self.a as u32 / 2) / self.a as u32).min(255) as u8;
+        Color {
+            r: div(self.r),
+            g: div(self.g),
+            b: div(self.b),
+            a: self.a,
+        }
+    }
+}
+
+impl Default for Color {
+    fn default() -> Self {
+        Self {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        }
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = Error;
+    /// Parses hex notation or a css color name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(color) = Color::named(s) {
+            return Ok(color);
+        }
+        Color::from_hex(s)
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+/// The css named colors, sorted by name.
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_hex() {
+        assert_eq!(
+            Color::from_hex("#00ff0080").unwrap(),
+            Color::rgba(0, 255, 0, 128)
+        );
+        assert_eq!(
+            Color::from_hex("336699").unwrap(),
+            Color::rgb(0x33, 0x66, 0x99)
+        );
+        assert_eq!(Color::from_hex("#0f0").unwrap(), Color::rgb(0, 255, 0));
+        assert_eq!(
+            Color::from_hex("#0f08").unwrap(),
+            Color::rgba(0, 255, 0, 0x88)
+        );
+        assert!(Color::from_hex("#0f").is_err());
+        assert!(Color::from_hex("#00gg00").is_err());
+        let c = Color::rgba(1, 2, 3, 4);
+        assert_eq!(Color::from_hex(&c.to_hex()).unwrap(), c);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(
+            "RebeccaPurple".parse::<Color>().unwrap(),
+            Color::rgb(0x66, 0x33, 0x99)
+        );
+        assert_eq!("transparent".parse::<Color>().unwrap(), Color::TRANSPARENT);
+        assert_eq!("#fff".parse::<Color>().unwrap(), Color::rgb(255, 255, 255));
+        assert!("notacolor".parse::<Color>().is_err());
+        assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_hsv_hsl() {
+        assert_eq!(Color::from_hsv(120.0, 1.0, 1.0), Color::rgb(0, 255, 0));
+        assert_eq!(Color::from_hsl(240.0, 1.0, 0.5), Color::rgb(0, 0, 255));
+        let c = Color::rgb(0x66, 0x33, 0x99);
+        let (h, s, v) = c.to_hsv();
+        assert_eq!(Color::from_hsv(h, s, v), c);
+        let (h, s, l) = c.to_hsl();
+        assert_eq!(Color::from_hsl(h, s, l), c);
+    }
+
+    #[test]
+    fn test_lerp_premultiply() {
+        let a = Color::rgba(0, 0, 0, 0);
+        let b = Color::rgba(200, 100, 50, 255);
+        assert_eq!(a.lerp(&b, 0.5), Color::rgba(100, 50, 25, 128));
+        let c = Color::rgba(255, 128, 0, 128);
+        assert_eq!(c.premultiplied(), Color::rgba(128, 64, 0, 128));
+        assert_eq!(c.premultiplied().unpremultiplied(), c);
+        assert_eq!(c.multiply(&Color::WHITE), c);
+        assert_eq!(c.multiply(&b), Color::rgba(200, 50, 0, 128));
+    }
+
+    #[test]
+    fn test_backend_conversions() {
+        assert_eq!(
+            Color::rgb(255, 255, 255).to_premultiplied_u16(),
+            [0xffff; 4]
+        );
+        assert_eq!(Color::TRANSPARENT.to_premultiplied_u16(), [0; 4]);
+        assert_eq!(
+            Color::rgba(255, 0, 255, 128).to_premultiplied_u16(),
+            [32896, 0, 32896, 32896]
+        );
+        assert_eq!(Color::rgb(0, 128, 0).to_premultiplied_u16()[1], 128 * 0x101);
+        assert_eq!(
+            Color::rgba(255, 0, 51, 0).to_premultiplied_u16(),
+            [0, 0, 0, 0]
+        );
+
+        assert_eq!(
+            Color::rgba(255, 0, 51, 128).to_rgba_f32(),
+            [1.0, 0.0, 0.2, 128.0 / 255.0]
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        #[derive(Deserialize)]
+        struct Config {
+            color: Color,
+        }
+        let parse = |s: &str| serde_json::from_str::<Config>(s).map(|c| c.color);
+        assert_eq!(
+            parse(r##"{"color": "#00ff0080"}"##).unwrap(),
+            Color::rgba(0, 255, 0, 128)
+        );
+        assert_eq!(parse(r#"{"color": "red"}"#).unwrap(), Color::rgb(255, 0, 0));
+        assert_eq!(
+            parse(r#"{"color": {"r": 1, "g": 2, "b": 3, "a": 4}}"#).unwrap(),
+            Color::rgba(1, 2, 3, 4)
+        );
+        assert!(parse(r##"{"color": "#12345"}"##).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Crosshairs centered on a monitor, for games that don't have one.
+//!
+//! The settings can be stored as a TOML or JSON profile, see [`CrosshairSettings::load`].
+
+use crate::{
+    Color, DrawGeometry, Error, GeometryElement, Group, LineStyle, Monitor, Overlay, Point, Stroke,
+    VisualToken,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// The shape of a crosshair.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum Shape {
+    /// Four arms around the center.
+    #[default]
+    Cross,
+    /// A filled dot as wide as the thickness.
+    Dot,
+    /// A circle with the size as radius.
+    Circle,
+    /// A cross without the top arm.
+    T,
+    /// A `^` below the center, its tip at the gap.
+    Chevron,
+}
+
+/// How a crosshair looks and where it is drawn.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CrosshairSettings {
+    pub shape: Shape,
+    pub color: Color,
+    /// The length of the arms, or the radius of the circle.
+    pub size: f32,
+    /// Space between the center and the arms.
+    pub gap: f32,
+    pub thickness: f32,
+    /// Drawn around the shape to keep it visible on any background, transparent for none.
+    pub outline: Color,
+    pub outline_thickness: f32,
+    /// Index into [`Overlay::monitors`], which lists the primary monitor first.
+    pub monitor: usize,
+    /// Moves the crosshair away from the center of the monitor.
+    pub offset: Point,
+}
+
+impl Default for CrosshairSettings {
+    fn default() -> Self {
+        Self {
+            shape: Shape::Cross,
+            color: Color::rgba(0, 255, 0, 192),
+            size: 10.0,
+            gap: 4.0,
+            thickness: 2.0,
+            outline: Color::rgba(0, 0, 0, 160),
+            outline_thickness: 1.0,
+            monitor: 0,
+            offset: Point::new(0.0, 0.0),
+        }
+    }
+}
+
+impl CrosshairSettings {
+    /// Load a profile, files ending in `.json` are read as JSON and everything else as TOML.
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<CrosshairSettings, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|e| e == "json") {
+            Ok(serde_json::from_str(&text)?)
+        } else {
+            Ok(toml::from_str(&text)?)
+        }
+    }
+
+    /// The center of the crosshair on `monitor`, on a pixel center when lines of this thickness need it to be crisp.
+    fn center(&self, monitor: &Monitor) -> Point {
+        let center = monitor.area.center() + self.offset;
+        let align = if (self.thickness.round() as i64) % 2 == 1 {
+            0.5
+        } else {
+            0.0
+        };
+        Point::new(center.x.floor() + align, center.y.floor() + align)
+    }
+
+    /// The shape around `center` with every free line end `extend` longer, which makes room for the outline.
+    fn geometry(&self, center: &Point, extend: f32) -> DrawGeometry {
+        let (size, gap) = (self.size, self.gap);
+        let arm = |direction: Point| {
+            let start = *center + direction * (gap - extend).max(0.0);
+            let end = *center + direction * (gap + size + extend);
+            [
+                GeometryElement::Start {
+                    start,
+                    filled: false,
+                },
+                GeometryElement::Line(end),
+                GeometryElement::End { closed: false },
+            ]
+        };
+        let (up, down, left, right) = (
+            Point::new(0.0, -1.0),
+            Point::new(0.0, 1.0),
+            Point::new(-1.0, 0.0),
+            Point::new(1.0, 0.0),
+        );
+        match self.shape {
+            Shape::Cross => DrawGeometry {
+                elements: [up, down, left, right].into_iter().flat_map(arm).collect(),
+            },
+            Shape::T => DrawGeometry {
+                elements: [down, left, right].into_iter().flat_map(arm).collect(),
+            },
+            Shape::Dot => {
+                // Stroked at half the thickness around a quarter radius, that fills the whole dot.
+                let mut dot = DrawGeometry::new().circle(center, self.thickness / 4.0);
+                if let Some(GeometryElement::Start { filled, .. }) = dot.elements.first_mut() {
+                    *filled = true;
+                }
+                dot
+            }
+            Shape::Circle => DrawGeometry::new().circle(center, size),
+            Shape::Chevron => {
+                let tip = *center + Point::new(0.0, gap);
+                let along = (size + extend) / std::f32::consts::SQRT_2;
+                DrawGeometry {
+                    elements: vec![
+                        GeometryElement::Start {
+                            start: tip + Point::new(-along, along),
+                            filled: false,
+                        },
+                        GeometryElement::Line(tip),
+                        GeometryElement::Line(tip + Point::new(along, along)),
+                        GeometryElement::End { closed: false },
+                    ],
+                }
+            }
+        }
+    }
+
+    /// The outline first and the crosshair over it, with the width they are stroked at.
+    fn layers(&self, center: &Point) -> Vec<(DrawGeometry, Stroke)> {
+        let width = match self.shape {
+            Shape::Dot => self.thickness / 2.0,
+            _ => self.thickness,
+        };
+        let mut layers = vec![];
+        if self.outline != Color::TRANSPARENT && self.outline_thickness > 0.0 {
+            layers.push((
+                self.geometry(center, self.outline_thickness),
+                Stroke {
+                    color: self.outline,
+                    width: width + 2.0 * self.outline_thickness,
+                },
+            ));
+        }
+        layers.push((
+            self.geometry(center, 0.0),
+            Stroke {
+                color: self.color,
+                width,
+            },
+        ));
+        layers
+    }
+}
+
+/// A crosshair on the overlay, removed when dropped.
+pub struct Crosshair {
+    overlay: Overlay,
+    group: Group,
+    /// Only locked inside a transaction on the overlay.
+    drawn: Mutex<Vec<VisualToken>>,
+}
+
+impl std::fmt::Debug for Crosshair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Crosshair")
+    }
+}
+
+impl Crosshair {
+    /// Fails on backends that can't draw geometry, see [`Overlay::draws_geometry`].
+    pub fn new(overlay: &Overlay, settings: &CrosshairSettings) -> Result<Crosshair, Error> {
+        if !overlay.draws_geometry() {
+            return Err("crosshairs need geometry, which this platform can't draw yet".into());
+        }
+        let crosshair = Crosshair {
+            overlay: overlay.clone(),
+            group: overlay.create_group()?,
+            drawn: Default::default(),
+        };
+        crosshair.set_settings(settings)?;
+        Ok(crosshair)
+    }
+
+    /// Redraw with new settings, the monitor's center is looked up again.
+    pub fn set_settings(&self, settings: &CrosshairSettings) -> Result<(), Error> {
+        self.overlay.transaction(|tx| {
+            let monitors = tx.monitors()?;
+            let monitor = monitors
+                .get(settings.monitor)
+                .ok_or_else(|| format!("there is no monitor {}", settings.monitor))?;
+            let center = settings.center(monitor);
+            let line_style = LineStyle::default();
+            let mut drawn = self.drawn.lock();
+            let mut visuals = vec![];
+            for (geometry, stroke) in settings.layers(&center) {
+                let token = tx.draw_geometry(&geometry, &stroke, &line_style)?;
+                self.group.add(&token)?;
+                visuals.push(token);
+            }
+            *drawn = visuals;
+            Ok(())
+        })
+    }
+
+    /// The group holding the crosshair's visuals.
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Rect;
+
+    #[test]
+    fn test_crosshair_settings() {
+        let toml = r##"
+            shape = "T"
+            color = "#ff000080"
+            gap = 3
+            thickness = 1
+            monitor = 1
+        "##;
+        let settings: CrosshairSettings = toml::from_str(toml).unwrap();
+        assert_eq!(settings.shape, Shape::T);
+        assert_eq!(settings.color, Color::rgba(255, 0, 0, 128));
+        assert_eq!(settings.size, 10.0);
+        let json =
+            r##"{"shape": "T", "color": "#ff000080", "gap": 3, "thickness": 1, "monitor": 1}"##;
+        assert_eq!(
+            serde_json::from_str::<CrosshairSettings>(json).unwrap(),
+            settings
+        );
+
+        let monitor = Monitor {
+            name: "screen 1".to_owned(),
+            area: Rect::from(1920.0, 0.0).sized(1920.0, 1080.0),
+            primary: false,
+        };
+        // An odd thickness sits on a pixel center.
+        let center = settings.center(&monitor);
+        assert_eq!(center, Point::new(2880.5, 540.5));
+
+        let geometry = settings.geometry(&center, 0.0);
+        assert_eq!(geometry.elements.len(), 9);
+        assert_eq!(
+            geometry.elements[1],
+            GeometryElement::Line(Point::new(2880.5, 540.5 + 13.0))
+        );
+        // The outline reaches past the ends of the arms.
+        let outline = settings.geometry(&center, 1.0);
+        assert_eq!(
+            outline.elements[1],
+            GeometryElement::Line(Point::new(2880.5, 540.5 + 14.0))
+        );
+        assert_eq!(settings.layers(&center).len(), 2);
+
+        let example: CrosshairSettings =
+            toml::from_str(include_str!("../examples/crosshair.toml")).unwrap();
+        assert_eq!(example, CrosshairSettings::default());
+    }
+}
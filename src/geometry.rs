@@ -0,0 +1,462 @@
+use crate::{CircleDirection, DrawGeometry, GeometryElement, Point, Rect};
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum distance in pixels between an arc and the line segments that approximate it.
+const FLATTEN_TOLERANCE: f32 = 0.1;
+
+/// An affine 2D transformation, laid out like a 3x2 matrix.
+///
+/// A point is transformed to `(x * m11 + y * m21 + dx, x * m12 + y * m22 + dy)`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform2D {
+    pub m11: f32,
+    pub m12: f32,
+    pub m21: f32,
+    pub m22: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+impl Default for Transform2D {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform2D {
+    pub const IDENTITY: Transform2D = Transform2D {
+        m11: 1.0,
+        m12: 0.0,
+        m21: 0.0,
+        m22: 1.0,
+        dx: 0.0,
+        dy: 0.0,
+    };
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self {
+            dx: x,
+            dy: y,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(sx: f32, sy: f32) -> Self {
+        Self {
+            m11: sx,
+            m22: sy,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Rotation about the origin, in degrees. Positive is clockwise on the screen.
+    pub fn rotation(degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            m11: cos,
+            m12: sin,
+            m21: -sin,
+            m22: cos,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Rotation about the provided center, in degrees.
+    pub fn rotation_about(degrees: f32, center: &Point) -> Self {
+        Self::translation(-center.x, -center.y)
+            .then(&Self::rotation(degrees))
+            .then(&Self::translation(center.x, center.y))
+    }
+
+    /// The transform that first applies `self` and then `other`.
+    pub fn then(&self, other: &Transform2D) -> Self {
+        Self {
+            m11: self.m11 * other.m11 + self.m12 * other.m21,
+            m12: self.m11 * other.m12 + self.m12 * other.m22,
+            m21: self.m21 * other.m11 + self.m22 * other.m21,
+            m22: self.m21 * other.m12 + self.m22 * other.m22,
+            dx: self.dx * other.m11 + self.dy * other.m21 + other.dx,
+            dy: self.dx * other.m12 + self.dy * other.m22 + other.dy,
+        }
+    }
+
+    pub fn determinant(&self) -> f32 {
+        self.m11 * self.m22 - self.m12 * self.m21
+    }
+
+    /// The inverse transform, `None` if the transform is not invertible.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det.abs() <= f32::EPSILON {
+            return None;
+        }
+        Some(Self {
+            m11: self.m22 / det,
+            m12: -self.m12 / det,
+            m21: -self.m21 / det,
+            m22: self.m11 / det,
+            dx: (self.m21 * self.dy - self.m22 * self.dx) / det,
+            dy: (self.m12 * self.dx - self.m11 * self.dy) / det,
+        })
+    }
+
+    pub fn transform_point(&self, p: &Point) -> Point {
+        Point {
+            x: p.x * self.m11 + p.y * self.m21 + self.dx,
+            y: p.x * self.m12 + p.y * self.m22 + self.dy,
+        }
+    }
+
+    /// The uniform scale factor if this transform maps circles onto circles.
+    fn uniform_scale(&self) -> Option<f32> {
+        let sx = self.m11.hypot(self.m12);
+        let sy = self.m21.hypot(self.m22);
+        let orthogonal = (self.m11 * self.m21 + self.m12 * self.m22).abs();
+        let tolerance = 1e-4 * sx.max(sy);
+        ((sx - sy).abs() <= tolerance && orthogonal <= tolerance).then_some(sx)
+    }
+}
+
+/// How the inside of a geometry is determined for self intersecting or nested figures.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum FillRule {
+    /// A point is inside if a ray from it crosses an odd number of edges.
+    #[default]
+    EvenOdd,
+    /// A point is inside if the winding number of the edges around it is not zero.
+    NonZero,
+}
+
+/// A figure approximated by line segments.
+struct Polyline {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+/// Approximate the arc from `start` to `end` by points, the `start` point itself is not included.
+fn flatten_arc(
+    start: &Point,
+    end: &Point,
+    radius: f32,
+    large: bool,
+    direction: CircleDirection,
+    points: &mut Vec<Point>,
+) {
+    let (hx, hy) = ((end.x - start.x) / 2.0, (end.y - start.y) / 2.0);
+    let half_chord = hx.hypot(hy);
+    if half_chord == 0.0 {
+        return;
+    }
+    // Like svg, a radius that's too small to span the endpoints is scaled up.
+    let radius = radius.abs().max(half_chord);
+    let h = (radius * radius - half_chord * half_chord).max(0.0).sqrt();
+    let (mx, my) = (start.x + hx, start.y + hy);
+    let (nx, ny) = (-hy / half_chord, hx / half_chord);
+
+    let mut best = None;
+    for side in [1.0, -1.0] {
+        let center = Point::new(mx + side * h * nx, my + side * h * ny);
+        let a0 = (start.y - center.y).atan2(start.x - center.x);
+        let a1 = (end.y - center.y).atan2(end.x - center.x);
+        // Angles increase clockwise on the screen, as y points down.
+        let sweep = match direction {
+            CircleDirection::ClockWise => (a1 - a0).rem_euclid(std::f32::consts::TAU),
+            CircleDirection::CounterClockWise => -(a0 - a1).rem_euclid(std::f32::consts::TAU),
+        };
+        if (sweep.abs() > std::f32::consts::PI) == large || best.is_none() {
+            best = Some((center, a0, sweep));
+        }
+    }
+    let (center, a0, sweep) = best.unwrap();
+
+    let step = 2.0 * (1.0 - FLATTEN_TOLERANCE / radius).clamp(-1.0, 1.0).acos();
+    let segments = if step > 0.0 {
+        (sweep.abs() / step).ceil().max(1.0) as usize
+    } else {
+        1
+    };
+    for i in 1..segments {
+        let a = a0 + sweep * i as f32 / segments as f32;
+        points.push(Point::new(
+            center.x + radius * a.cos(),
+            center.y + radius * a.sin(),
+        ));
+    }
+    points.push(*end);
+}
+
+/// Distance from `p` to the segment between `a` and `b`.
+fn segment_distance(p: &Point, a: &Point, b: &Point) -> f32 {
+    let d = *b - *a;
+    let length_sq = d.x * d.x + d.y * d.y;
+    let t = if length_sq > 0.0 {
+        (((p.x - a.x) * d.x + (p.y - a.y) * d.y) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (*p - (*a + d * t)).length()
+}
+
+impl DrawGeometry {
+    /// Approximate all figures by line segments.
+    fn flatten(&self) -> Vec<Polyline> {
+        let mut figures = vec![];
+        let mut current: Option<Polyline> = None;
+        for el in self.elements.iter() {
+            match el {
+                GeometryElement::Start { start, .. } => {
+                    figures.extend(current.take());
+                    current = Some(Polyline {
+                        points: vec![*start],
+                        closed: false,
+                    });
+                }
+                GeometryElement::Line(point) => {
+                    if let Some(figure) = current.as_mut() {
+                        figure.points.push(*point);
+                    }
+                }
+                GeometryElement::Arc {
+                    end_point,
+                    radius,
+                    angle,
+                    direction,
+                } => {
+                    if let Some(figure) = current.as_mut() {
+                        let start = *figure.points.last().unwrap();
+                        // Same arc size selection as the drawing backend.
+                        let large = *angle >= 180.0;
+                        flatten_arc(
+                            &start,
+                            end_point,
+                            *radius,
+                            large,
+                            *direction,
+                            &mut figure.points,
+                        );
+                    }
+                }
+                GeometryElement::End { closed } => {
+                    if let Some(mut figure) = current.take() {
+                        figure.closed = *closed;
+                        figures.push(figure);
+                    }
+                }
+            }
+        }
+        figures.extend(current.take());
+        figures
+    }
+
+    /// The bounding rectangle of the geometry when drawn with a stroke of `stroke_width`.
+    ///
+    /// The stroke extends half its width on either side, miter joins may extend slightly further. An empty geometry
+    /// returns an empty rectangle at the origin.
+    pub fn bounds(&self, stroke_width: f32) -> Rect {
+        let mut points = self.flatten().into_iter().flat_map(|f| f.points);
+        let Some(first) = points.next() else {
+            return Rect::from(0.0, 0.0);
+        };
+        points
+            .fold(Rect::new(first, first), |r, p| r.union(&Rect::new(p, p)))
+            .expand(stroke_width / 2.0)
+    }
+
+    /// Apply a transform to all elements.
+    ///
+    /// Arcs stay arcs if the transform maps circles onto circles, otherwise they are approximated by lines.
+    pub fn transformed(&self, transform: &Transform2D) -> Self {
+        let scale = transform.uniform_scale();
+        let mirrored = transform.determinant() < 0.0;
+        let mut elements = Vec::with_capacity(self.elements.len());
+        let mut current = Point::ORIGIN;
+        for el in self.elements.iter() {
+            match el {
+                GeometryElement::Start { start, filled } => {
+                    current = *start;
+                    elements.push(GeometryElement::Start {
+                        start: transform.transform_point(start),
+                        filled: *filled,
+                    });
+                }
+                GeometryElement::Line(point) => {
+                    current = *point;
+                    elements.push(GeometryElement::Line(transform.transform_point(point)));
+                }
+                GeometryElement::Arc {
+                    end_point,
+                    radius,
+                    angle,
+                    direction,
+                } => {
+                    if let Some(scale) = scale {
+                        let direction = match (direction, mirrored) {
+                            (CircleDirection::ClockWise, true) => CircleDirection::CounterClockWise,
+                            (CircleDirection::CounterClockWise, true) => CircleDirection::ClockWise,
+                            (d, false) => *d,
+                        };
+                        elements.push(GeometryElement::Arc {
+                            end_point: transform.transform_point(end_point),
+                            radius: radius * scale,
+                            angle: *angle,
+                            direction,
+                        });
+                    } else {
+                        let mut points = vec![];
+                        let large = *angle >= 180.0;
+                        flatten_arc(&current, end_point, *radius, large, *direction, &mut points);
+                        elements.extend(
+                            points
+                                .iter()
+                                .map(|p| GeometryElement::Line(transform.transform_point(p))),
+                        );
+                    }
+                    current = *end_point;
+                }
+                GeometryElement::End { closed } => {
+                    elements.push(GeometryElement::End { closed: *closed });
+                }
+            }
+        }
+        Self { elements }
+    }
+
+    /// Move all elements by the provided offset.
+    pub fn translated(&self, offset: Point) -> Self {
+        self.transformed(&Transform2D::translation(offset.x, offset.y))
+    }
+
+    /// Returns true if the point lies in the area enclosed by the figures.
+    ///
+    /// Every figure is treated as closed for this, regardless of whether it is filled when drawn.
+    pub fn contains(&self, point: Point, rule: FillRule) -> bool {
+        let mut winding = 0i32;
+        let mut crossings = 0u32;
+        for figure in self.flatten() {
+            let n = figure.points.len();
+            for i in 0..n {
+                let a = &figure.points[i];
+                let b = &figure.points[(i + 1) % n];
+                let upward = a.y <= point.y && b.y > point.y;
+                let downward = b.y <= point.y && a.y > point.y;
+                if !(upward || downward) {
+                    continue;
+                }
+                let x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if x > point.x {
+                    crossings += 1;
+                    winding += if upward { 1 } else { -1 };
+                }
+            }
+        }
+        match rule {
+            FillRule::EvenOdd => crossings % 2 == 1,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+
+    /// Returns true if the point lies on the stroke when the geometry is drawn with a stroke of `width`.
+    pub fn stroke_contains(&self, point: Point, width: f32) -> bool {
+        let half = width / 2.0;
+        self.flatten().iter().any(|figure| {
+            let segments = figure.points.windows(2).map(|w| (&w[0], &w[1]));
+            let closing = figure
+                .closed
+                .then(|| (figure.points.last().unwrap(), &figure.points[0]));
+            let mut segments = segments.chain(closing).peekable();
+            if segments.peek().is_none() {
+                return (point - figure.points[0]).length() <= half;
+            }
+            segments.any(|(a, b)| segment_distance(&point, a, b) <= half)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn test_transform_compose_inverse() {
+        let t = Transform2D::rotation(30.0)
+            .then(&Transform2D::scale(2.0, 3.0))
+            .then(&Transform2D::translation(5.0, -1.0));
+        let p = Point::new(3.0, 4.0);
+        let back = t.inverse().unwrap().transform_point(&t.transform_point(&p));
+        assert!(approx(back.x, p.x) && approx(back.y, p.y));
+
+        let r = Transform2D::rotation(90.0).transform_point(&Point::new(1.0, 0.0));
+        assert!(approx(r.x, 0.0) && approx(r.y, 1.0));
+        assert!(Transform2D::scale(0.0, 1.0).inverse().is_none());
+    }
+
+    #[test]
+    fn test_bounds() {
+        let rect = Rect::from(10.0, 20.0).sized(30.0, 40.0);
+        let b = DrawGeometry::new().rectangle(&rect).bounds(2.0);
+        assert_eq!(b, Rect::from(9.0, 19.0).sized(32.0, 42.0));
+
+        let b = DrawGeometry::new()
+            .circle(&Point::new(100.0, 100.0), 10.0)
+            .bounds(0.0);
+        assert!(approx(b.min.x, 90.0) && approx(b.max.x, 110.0));
+        assert!(approx(b.min.y, 90.0) && approx(b.max.y, 110.0));
+
+        assert_eq!(DrawGeometry::new().bounds(4.0), Rect::from(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_transformed() {
+        let g = DrawGeometry::new().circle(&Point::new(0.0, 0.0), 10.0);
+        let moved = g.translated(Point::new(5.0, 5.0)).bounds(0.0);
+        assert!(approx(moved.min.x, -5.0) && approx(moved.max.y, 15.0));
+
+        let scaled = g.transformed(&Transform2D::scale(2.0, 2.0));
+        assert!(matches!(
+            scaled.elements[1],
+            GeometryElement::Arc { radius, .. } if approx(radius, 20.0)
+        ));
+
+        // Non uniform scaling turns the arcs into lines, resulting in an ellipse.
+        let stretched = g.transformed(&Transform2D::scale(2.0, 1.0));
+        assert!(stretched
+            .elements
+            .iter()
+            .all(|e| !matches!(e, GeometryElement::Arc { .. })));
+        let b = stretched.bounds(0.0);
+        assert!(approx(b.max.x, 20.0) && approx(b.max.y, 10.0));
+    }
+
+    #[test]
+    fn test_contains() {
+        let outer = Rect::from(0.0, 0.0).sized(100.0, 100.0);
+        let inner = Rect::from(25.0, 25.0).sized(50.0, 50.0);
+        let g = DrawGeometry::new().rectangle(&outer).rectangle(&inner);
+        assert!(g.contains(Point::new(10.0, 10.0), FillRule::EvenOdd));
+        assert!(!g.contains(Point::new(50.0, 50.0), FillRule::EvenOdd));
+        // Both rectangles wind the same way, so the center stays inside.
+        assert!(g.contains(Point::new(50.0, 50.0), FillRule::NonZero));
+        assert!(!g.contains(Point::new(150.0, 50.0), FillRule::NonZero));
+
+        let circle = DrawGeometry::new().circle(&Point::new(0.0, 0.0), 10.0);
+        assert!(circle.contains(Point::new(6.0, 6.0), FillRule::EvenOdd));
+        assert!(!circle.contains(Point::new(8.0, 8.0), FillRule::EvenOdd));
+    }
+
+    #[test]
+    fn test_stroke_contains() {
+        let g = DrawGeometry::new().line_segment(&Point::new(0.0, 0.0), &Point::new(100.0, 0.0));
+        assert!(g.stroke_contains(Point::new(50.0, 1.5), 4.0));
+        assert!(!g.stroke_contains(Point::new(50.0, 2.5), 4.0));
+        assert!(!g.stroke_contains(Point::new(103.0, 0.0), 4.0));
+
+        let circle = DrawGeometry::new().circle(&Point::new(0.0, 0.0), 10.0);
+        assert!(circle.stroke_contains(Point::new(0.0, 10.5), 2.0));
+        assert!(!circle.stroke_contains(Point::new(0.0, 0.0), 2.0));
+    }
+}
@@ -1,4 +1,5 @@
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
@@ -20,38 +21,434 @@ mod backend;
 #[cfg_attr(target_os = "windows", path = "windows.rs")]
 mod backend;
 
-use backend::{IDVisual, ImageTexture, OverlayImpl, PreparedFont};
+mod geometry;
+pub use geometry::{FillRule, Transform2D};
+
+mod color;
+pub use color::Color;
+
+mod svg;
+
+mod tree;
+
+mod scheduler;
+use scheduler::{Scheduler, TaskId};
+
+mod cache;
+pub use cache::{TextureCache, TextureKey};
+
+mod animated;
+pub use animated::{AnimatedTexture, AnimationToken};
+
+pub mod atlas;
+pub use atlas::Atlas;
+
+mod tween;
+pub use tween::{Easing, Property, Tween, TweenToken, Value};
+
+pub mod widgets;
+
+pub mod crosshair;
+
+pub mod scene;
+pub use scene::{LoadedScene, Scene};
+
+pub mod watch;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+use backend::{IDVisual, OverlayImpl, PreparedFont};
+pub use backend::{ImageTexture, WeakImageTexture};
 
 pub use backend::run_msg_loop as block_and_loop;
 pub use backend::setup;
 
-use parking_lot::Mutex;
+use parking_lot::{Mutex, ReentrantMutex, ReentrantMutexGuard};
+use std::cell::RefCell;
+
 pub type Error = Box<dyn std::error::Error + Send + Sync + 'static>;
 
+/// The lock is reentrant so handles can be dropped and drawn on the thread that holds a [`Transaction`], the
+/// backend is only borrowed for the duration of a single call.
+type LockedOverlay = ReentrantMutex<RefCell<OverlayImpl>>;
+
+/// Time between opacity updates of a visual that fades out before it expires.
+const FADE_INTERVAL: Duration = Duration::from_millis(16);
+
+struct VisualInner {
+    overlay: Arc<LockedOverlay>,
+    /// The rest of the overlay, for the scheduler that runs tweens.
+    owner: WeakOverlay,
+    visual: IDVisual,
+    /// Only locked while holding the overlay's lock, so changes are applied in the order they are made.
+    placement: Mutex<Placement>,
+    /// The task set up by [`VisualToken::expire_after`], only locked while holding the overlay's lock.
+    expiry: Mutex<Option<TaskId>>,
+}
+
+impl Drop for VisualInner {
+    fn drop(&mut self) {
+        // println!("dropping");
+        let guard = self.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock
+            .remove_visual(&self.visual)
+            .expect("removing something thats already removed");
+    }
+}
+
+/// The transform of a visual, split into parts that can be changed and animated independently.
+#[derive(Copy, Clone, Debug)]
+struct Placement {
+    /// Set by [`VisualToken::set_transform`], the other parts apply after it.
+    base: Transform2D,
+    position: Point,
+    scale: f32,
+    rotation: f32,
+    pivot: Point,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self {
+            base: Transform2D::IDENTITY,
+            position: Point::ORIGIN,
+            scale: 1.0,
+            rotation: 0.0,
+            pivot: Point::ORIGIN,
+        }
+    }
+}
+
+impl Placement {
+    fn transform(&self) -> Transform2D {
+        let Placement {
+            pivot, position, ..
+        } = *self;
+        self.base
+            .then(&Transform2D::translation(-pivot.x, -pivot.y))
+            .then(&Transform2D::scale(self.scale, self.scale))
+            .then(&Transform2D::rotation(self.rotation))
+            .then(&Transform2D::translation(
+                pivot.x + position.x,
+                pivot.y + position.y,
+            ))
+    }
+}
+
+/// Handle to something drawn on the overlay, it is removed when the last clone of the handle is dropped.
+///
+/// The properties set here combine with those of the groups the visual is in.
 #[derive(Clone)]
 pub struct VisualToken {
-    overlay: Arc<Mutex<OverlayImpl>>,
-    visual: IDVisual,
+    inner: Arc<VisualInner>,
 }
 impl std::fmt::Debug for VisualToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "VisualToken {:?}", &self.visual)
+        write!(f, "VisualToken {:?}", &self.inner.visual)
     }
 }
 
-impl Drop for VisualToken {
+impl VisualToken {
+    fn new(overlay: &Overlay, visual: IDVisual) -> Self {
+        Self {
+            inner: Arc::new(VisualInner {
+                overlay: overlay.overlay.clone(),
+                owner: overlay.downgrade(),
+                visual,
+                placement: Default::default(),
+                expiry: Default::default(),
+            }),
+        }
+    }
+
+    fn visual(&self) -> &IDVisual {
+        &self.inner.visual
+    }
+
+    /// The overlay this visual is on, `None` once the last handle to the overlay is dropped.
+    pub(crate) fn overlay(&self) -> Option<Overlay> {
+        self.inner.owner.upgrade()
+    }
+
+    /// Stacking order among the siblings in the same group, higher is on top. Defaults to 0, among equal values
+    /// the most recently drawn or changed is on top.
+    pub fn set_z_index(&self, z_index: i32) -> std::result::Result<(), Error> {
+        let guard = self.inner.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.set_z_index(self.visual(), z_index)
+    }
+
+    /// Hide or show the visual, hiding keeps it in the overlay so showing it again doesn't need to draw it again.
+    pub fn set_visible(&self, visible: bool) -> std::result::Result<(), Error> {
+        let guard = self.inner.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.set_visible(self.visual(), visible)
+    }
+
+    /// Whether the visual itself is visible, it is still hidden if a group it is in is hidden.
+    pub fn is_visible(&self) -> bool {
+        let guard = self.inner.overlay.lock();
+        let wlock = guard.borrow();
+        wlock.is_visible(self.visual())
+    }
+
+    /// The z-index, opacity and tint set on the visual itself.
+    fn appearance(&self) -> Option<tree::Appearance> {
+        let guard = self.inner.overlay.lock();
+        let wlock = guard.borrow();
+        wlock.appearance(self.visual())
+    }
+
+    pub fn z_index(&self) -> i32 {
+        self.appearance().map_or(0, |a| a.z_index)
+    }
+
+    /// Opacity multiplied into the visual, 0.0 is transparent, 1.0 is opaque.
+    pub fn set_opacity(&self, opacity: f32) -> std::result::Result<(), Error> {
+        let guard = self.inner.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.set_opacity(self.visual(), opacity.clamp(0.0, 1.0))
+    }
+
+    /// Color multiplied into the visual's colors, white leaves them unchanged.
+    pub fn set_tint(&self, tint: &Color) -> std::result::Result<(), Error> {
+        let guard = self.inner.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.set_tint(self.visual(), tint)
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.appearance().map_or(1.0, |a| a.opacity)
+    }
+
+    pub fn tint(&self) -> Color {
+        self.appearance().map_or(Color::WHITE, |a| a.tint)
+    }
+
+    /// Transform applied to the visual, before its scale, rotation and position.
+    ///
+    /// On X11 text only follows the translation of the transform.
+    pub fn set_transform(&self, transform: &Transform2D) -> std::result::Result<(), Error> {
+        self.update_placement(|p| p.base = *transform)
+    }
+
+    /// Offset of the visual from where it was drawn.
+    pub fn set_position(&self, position: &Point) -> std::result::Result<(), Error> {
+        self.update_placement(|p| p.position = *position)
+    }
+
+    /// Uniform scale about the pivot.
+    pub fn set_scale(&self, scale: f32) -> std::result::Result<(), Error> {
+        self.update_placement(|p| p.scale = scale)
+    }
+
+    /// Rotation in degrees about the pivot, positive is clockwise.
+    pub fn set_rotation(&self, degrees: f32) -> std::result::Result<(), Error> {
+        self.update_placement(|p| p.rotation = degrees)
+    }
+
+    /// The point in overlay coordinates to scale and rotate about, defaults to the origin.
+    pub fn set_pivot(&self, pivot: &Point) -> std::result::Result<(), Error> {
+        self.update_placement(|p| p.pivot = *pivot)
+    }
+
+    fn placement(&self) -> Placement {
+        let _guard = self.inner.overlay.lock();
+        *self.inner.placement.lock()
+    }
+
+    pub fn position(&self) -> Point {
+        self.placement().position
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.placement().scale
+    }
+
+    pub fn rotation(&self) -> f32 {
+        self.placement().rotation
+    }
+
+    fn update_placement<F: FnOnce(&mut Placement)>(&self, f: F) -> std::result::Result<(), Error> {
+        let guard = self.inner.overlay.lock();
+        let mut placement = self.inner.placement.lock();
+        f(&mut placement);
+        let mut wlock = guard.borrow_mut();
+        wlock.set_transform(self.visual(), &placement.transform())
+    }
+
+    /// Animate one of the visual's properties, see [`Tween`].
+    ///
+    /// The tween stops when the returned token is dropped, unless it is detached.
+    pub fn animate(&self, tween: &Tween) -> std::result::Result<TweenToken, Error> {
+        TweenToken::new(self, tween)
+    }
+
+    /// Remove the visual after `duration`, the overlay holds on to it until then so the token can be dropped.
+    ///
+    /// If other handles to the visual are still held when it expires it is hidden instead, it is removed once they
+    /// are dropped. Calling this again replaces the earlier expiry.
+    pub fn expire_after(&self, duration: Duration) -> std::result::Result<(), Error> {
+        self.expire_after_with_fade(duration, Duration::ZERO)
+    }
+
+    /// Like [`VisualToken::expire_after`], with the opacity fading out to zero during the last `fade` of the
+    /// duration. Replacing the expiry while the visual fades stops the fade where it is.
+    pub fn expire_after_with_fade(
+        &self,
+        duration: Duration,
+        fade: Duration,
+    ) -> std::result::Result<(), Error> {
+        let overlay = self.overlay().ok_or("the overlay no longer exists")?;
+        let fade = fade.min(duration);
+        let start = Instant::now();
+        let end = start + duration;
+        let mut visual = Some(self.clone());
+        // The opacity when the fade started.
+        let mut faded_from = None;
+        let task: scheduler::Task = Box::new(move |overlay, now| {
+            if now < end {
+                let token = visual.as_ref()?;
+                let from = *faded_from.get_or_insert_with(|| token.opacity());
+                let elapsed = now.saturating_duration_since(start);
+                let _ = token.set_opacity(fade_opacity(from, elapsed, duration, fade));
+                return Some((now + FADE_INTERVAL).min(end));
+            }
+            let _ = overlay.transaction(|_| match visual.take() {
+                Some(token) => token.set_visible(false),
+                None => Ok(()),
+            });
+            None
+        });
+
+        let _guard = self.inner.overlay.lock();
+        let mut expiry = self.inner.expiry.lock();
+        let id = overlay.scheduler.schedule(end - fade, task);
+        if let Some(previous) = expiry.replace(id) {
+            overlay.scheduler.cancel(previous);
+        }
+        Ok(())
+    }
+}
+
+/// The opacity `elapsed` into [`VisualToken::expire_after_with_fade`], for a visual at `from` before it fades.
+///
+/// The fade takes the last `fade` of the `duration`, or all of it if it is longer.
+fn fade_opacity(from: f32, elapsed: Duration, duration: Duration, fade: Duration) -> f32 {
+    let fade = fade.min(duration);
+    let remaining = duration.saturating_sub(elapsed);
+    if remaining.is_zero() {
+        0.0
+    } else if remaining >= fade {
+        from
+    } else {
+        from * remaining.as_secs_f32() / fade.as_secs_f32()
+    }
+}
+
+/// A node in the overlay's visual tree that holds visuals and other groups.
+///
+/// The group dereferences to its [`VisualToken`], the z-index, visibility, transform, opacity and tint set there
+/// apply to everything in the group. The group is removed from the overlay when the last clone of the handle is
+/// dropped, everything in it is removed with it; even visuals and groups whose handles are still held.
+#[derive(Clone, Debug)]
+pub struct Group {
+    token: VisualToken,
+}
+
+impl Group {
+    /// Create an empty group inside this group.
+    pub fn create_group(&self) -> std::result::Result<Group, Error> {
+        let overlay = self.token.overlay().ok_or("the overlay no longer exists")?;
+        let group = overlay.create_group()?;
+        self.add_group(&group)?;
+        Ok(group)
+    }
+
+    /// Move a visual into this group, it is placed on top of the group's children with the same z-index.
+    pub fn add(&self, visual: &VisualToken) -> std::result::Result<(), Error> {
+        let guard = self.token.inner.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.set_parent(visual.visual(), self.token.visual())
+    }
+
+    /// Move another group into this group, fails if that would place a group inside itself.
+    pub fn add_group(&self, group: &Group) -> std::result::Result<(), Error> {
+        self.add(&group.token)
+    }
+}
+
+impl std::ops::Deref for Group {
+    type Target = VisualToken;
+    fn deref(&self) -> &VisualToken {
+        &self.token
+    }
+}
+
+/// An open transaction on the overlay, see [`Overlay::transaction`].
+///
+/// Dereferences to the [`Overlay`], so everything that can be drawn on the overlay can be drawn in a transaction.
+pub struct Transaction<'a> {
+    overlay: &'a Overlay,
+    /// Holds the overlay for the duration of the transaction, `None` once it is finished.
+    guard: Option<ReentrantMutexGuard<'a, RefCell<OverlayImpl>>>,
+}
+
+impl Transaction<'_> {
+    fn finish(&mut self) -> std::result::Result<(), Error> {
+        match self.guard.take() {
+            Some(guard) => Ok(guard.borrow_mut().end_batch()?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::ops::Deref for Transaction<'_> {
+    type Target = Overlay;
+    fn deref(&self) -> &Overlay {
+        self.overlay
+    }
+}
+
+impl Drop for Transaction<'_> {
     fn drop(&mut self) {
-        // println!("dropping");
-        let mut wlock = self.overlay.lock();
-        wlock
-            .remove_visual(&self.visual)
-            .expect("removing something thats already removed");
+        // Only reached without finishing if the closure panicked, the changes are still shown.
+        let _ = self.finish();
     }
 }
 
 #[derive(Clone)]
 pub struct Overlay {
-    overlay: Arc<Mutex<OverlayImpl>>,
+    overlay: Arc<LockedOverlay>,
+    scheduler: Arc<Scheduler>,
+    textures: Arc<TextureCache>,
+}
+
+/// An overlay reference that doesn't keep the overlay alive, used by the scheduler's thread.
+#[derive(Clone)]
+pub(crate) struct WeakOverlay {
+    overlay: Weak<LockedOverlay>,
+    scheduler: Weak<Scheduler>,
+    textures: Weak<TextureCache>,
+}
+impl WeakOverlay {
+    pub fn upgrade(&self) -> Option<Overlay> {
+        Some(Overlay {
+            overlay: self.overlay.upgrade()?,
+            scheduler: self.scheduler.upgrade()?,
+            textures: self.textures.upgrade()?,
+        })
+    }
+}
+
+/// A monitor and the part of the overlay it shows.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Monitor {
+    pub name: String,
+    pub area: Rect,
+    pub primary: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -82,19 +479,40 @@ impl Overlay {
     }
 
     pub fn new_with_config(config: &OverlayConfig) -> std::result::Result<Overlay, Error> {
-        let window = Arc::new(Mutex::new(OverlayImpl::new()?));
+        let window = Arc::new(ReentrantMutex::new(RefCell::new(OverlayImpl::new()?)));
         {
-            let mut wlock = window.lock();
+            let guard = window.lock();
+            let mut wlock = guard.borrow_mut();
             wlock.create_window(config)?;
             wlock.create_device_resources()?;
         }
-        Ok(Self { overlay: window })
+        let textures = Arc::new(TextureCache::default());
+        let scheduler = Arc::new_cyclic(|scheduler| {
+            Scheduler::new(WeakOverlay {
+                overlay: Arc::downgrade(&window),
+                scheduler: scheduler.clone(),
+                textures: Arc::downgrade(&textures),
+            })
+        });
+        Ok(Self {
+            overlay: window,
+            scheduler,
+            textures,
+        })
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakOverlay {
+        WeakOverlay {
+            overlay: Arc::downgrade(&self.overlay),
+            scheduler: Arc::downgrade(&self.scheduler),
+            textures: Arc::downgrade(&self.textures),
+        }
     }
 
     /// Prepare a font for usage.
     ///
     /// Draw arbitrary geometry on the screen. You may need to offset by half a pixel to ensure you get pixel-perfect
-    /// crisp lines.
+    /// crisp lines. Figures that start `filled` are filled with the stroke color before the outline is drawn.
     pub fn draw_geometry(
         &self,
         geometry: &DrawGeometry,
@@ -102,12 +520,10 @@ impl Overlay {
         line_style: &LineStyle,
     ) -> std::result::Result<VisualToken, Error> {
         {
-            let mut wlock = self.overlay.lock();
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
             let visual = wlock.draw_geometry(geometry, stroke, line_style)?;
-            Ok(VisualToken {
-                visual,
-                overlay: self.overlay.clone(),
-            })
+            Ok(VisualToken::new(self, visual))
         }
     }
 
@@ -118,8 +534,9 @@ impl Overlay {
         &self,
         properties: &TextProperties,
     ) -> std::result::Result<PreparedFont, Error> {
-        let mut wlock = self.overlay.lock();
-        Ok(wlock.prepare_font(properties)?)
+        let guard = self.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.prepare_font(properties)
     }
 
     /// Draw text on the screen.
@@ -136,12 +553,10 @@ impl Overlay {
         font: &PreparedFont,
     ) -> std::result::Result<VisualToken, Error> {
         {
-            let mut wlock = self.overlay.lock();
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
             let visual = wlock.draw_text(text, layout, color, font)?;
-            Ok(VisualToken {
-                visual,
-                overlay: self.overlay.clone(),
-            })
+            Ok(VisualToken::new(self, visual))
         }
     }
 
@@ -151,8 +566,193 @@ impl Overlay {
         path: P,
     ) -> std::result::Result<ImageTexture, Error> {
         {
-            let mut wlock = self.overlay.lock();
-            Ok(wlock.load_texture(path)?)
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
+            let texture = wlock.load_texture(path)?;
+            texture.set_owner(self.downgrade());
+            Ok(texture)
+        }
+    }
+
+    /// Load a texture from an encoded image in memory, for example one embedded with `include_bytes!`.
+    ///
+    /// The format is detected from the data, PNG, JPEG, BMP, GIF and WebP are supported. Only the first frame of
+    /// animated images is loaded.
+    pub fn load_texture_from_bytes(&self, data: &[u8]) -> std::result::Result<ImageTexture, Error> {
+        let format = ImageFormat::detect(data).ok_or("unsupported or unrecognised image format")?;
+        {
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
+            let texture = wlock.load_texture_from_bytes(data, format)?;
+            texture.set_owner(self.downgrade());
+            Ok(texture)
+        }
+    }
+
+    /// Make a set of changes that become visible together, instead of one by one.
+    ///
+    /// The closure gets a [`Transaction`] that can be used like the overlay itself; everything drawn, changed or
+    /// removed while it runs is shown at once when it returns, including visuals whose tokens are dropped inside
+    /// it. Other threads wait for the transaction to finish before they can change the overlay. There is no
+    /// rollback, if the closure fails the changes it made so far are still shown. Transactions can be nested, only
+    /// the outermost one makes the changes visible.
+    pub fn transaction<R, F>(&self, f: F) -> std::result::Result<R, Error>
+    where
+        F: FnOnce(&Transaction) -> std::result::Result<R, Error>,
+    {
+        let guard = self.overlay.lock();
+        guard.borrow_mut().begin_batch();
+        let mut transaction = Transaction {
+            overlay: self,
+            guard: Some(guard),
+        };
+        let result = f(&transaction);
+        let committed = transaction.finish();
+        let value = result?;
+        committed?;
+        Ok(value)
+    }
+
+    /// Hide or show everything on the overlay, for example to hide a HUD during cutscenes.
+    ///
+    /// Visuals are kept while hidden, they can still be drawn, changed and removed and show up as they are when the
+    /// overlay is shown again.
+    pub fn set_visible(&self, visible: bool) -> std::result::Result<(), Error> {
+        let guard = self.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        wlock.set_visible(&IDVisual::ROOT, visible)
+    }
+
+    pub fn is_visible(&self) -> bool {
+        let guard = self.overlay.lock();
+        let wlock = guard.borrow();
+        wlock.is_visible(&IDVisual::ROOT)
+    }
+
+    /// Whether [`Overlay::draw_geometry`] shows anything, it doesn't on X11 yet and returns visuals without content.
+    pub fn draws_geometry(&self) -> bool {
+        backend::DRAWS_GEOMETRY
+    }
+
+    /// The monitors with their area in overlay coordinates, the primary monitor first.
+    ///
+    /// On Windows the overlay only covers the primary monitor, drawing in the area of another one isn't visible.
+    pub fn monitors(&self) -> std::result::Result<Vec<Monitor>, Error> {
+        let guard = self.overlay.lock();
+        let wlock = guard.borrow();
+        let mut monitors = wlock.monitors()?;
+        monitors.sort_by_key(|m| !m.primary);
+        Ok(monitors)
+    }
+
+    /// Create an empty group, drawn visuals can be moved into it with [`Group::add`].
+    pub fn create_group(&self) -> std::result::Result<Group, Error> {
+        let guard = self.overlay.lock();
+        let mut wlock = guard.borrow_mut();
+        let visual = wlock.create_group()?;
+        Ok(Group {
+            token: VisualToken::new(self, visual),
+        })
+    }
+
+    /// The overlay wide cache used by [`Overlay::load_texture_cached`] and [`Overlay::load_texture_from_bytes_cached`].
+    pub fn texture_cache(&self) -> &TextureCache {
+        &self.textures
+    }
+
+    /// Load a texture from disk, or return the cached texture if this file is already loaded and still in use.
+    ///
+    /// Changes to the file are not picked up while the texture is cached, evict it from the cache to reload it.
+    /// All handles share the texture, so [`ImageTexture::update`] affects every user.
+    pub fn load_texture_cached<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> std::result::Result<ImageTexture, Error> {
+        let path = path.as_ref();
+        self.textures
+            .get_or_load(TextureKey::path(path), || self.load_texture(path))
+    }
+
+    /// Load a texture from encoded data, or return the cached texture if identical data is already loaded.
+    pub fn load_texture_from_bytes_cached(
+        &self,
+        data: &[u8],
+    ) -> std::result::Result<ImageTexture, Error> {
+        self.textures.get_or_load(TextureKey::content(data), || {
+            self.load_texture_from_bytes(data)
+        })
+    }
+
+    /// Load an animated GIF, PNG or WebP file, each frame becomes a texture.
+    ///
+    /// Still images load as an animation with a single frame.
+    pub fn load_animation<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+    ) -> std::result::Result<AnimatedTexture, Error> {
+        let data = std::fs::read(path)?;
+        self.load_animation_from_bytes(&data)
+    }
+
+    /// Load an animated image from encoded data in memory, see [`Overlay::load_animation`].
+    pub fn load_animation_from_bytes(
+        &self,
+        data: &[u8],
+    ) -> std::result::Result<AnimatedTexture, Error> {
+        let format = ImageFormat::detect(data).ok_or("unsupported or unrecognised image format")?;
+        let frames = animated::decode_frames(data, format)?;
+        AnimatedTexture::create(self, frames)
+    }
+
+    /// Draw an animation, it plays from the first frame and repeats until the returned token is dropped.
+    ///
+    /// The frames are advanced on a background thread owned by the overlay, the token controls playback.
+    pub fn draw_animation(
+        &self,
+        animation: &AnimatedTexture,
+        options: &DrawTextureOptions,
+    ) -> std::result::Result<AnimationToken, Error> {
+        AnimationToken::new(self, animation, options)
+    }
+
+    /// Load an svg file from disk and rasterize it into a texture for later use.
+    ///
+    /// * `path` The svg file to load.
+    /// * `size` The width and height in pixels, the document is scaled to fill this size.
+    ///
+    /// Rasterization happens on the cpu, the result is used like any other texture with [`draw_texture`].
+    pub fn load_svg<P: AsRef<std::path::Path>>(
+        &self,
+        path: P,
+        size: (u32, u32),
+    ) -> std::result::Result<ImageTexture, Error> {
+        let (width, height) = size;
+        let data = svg::rasterize(path, width, height)?;
+        self.create_texture(width, height, &data, PixelFormat::Rgba8Premultiplied)
+    }
+
+    /// Create a texture from pixels in memory.
+    ///
+    /// * `width` The width of the image in pixels.
+    /// * `height` The height of the image in pixels.
+    /// * `data` Rows of pixels without padding, 4 bytes per pixel.
+    /// * `format` The layout of the pixels in `data`.
+    ///
+    /// The contents can be replaced later with `ImageTexture::update`, this is cheaper than creating a new texture and
+    /// visuals that already show the texture are redrawn with the new contents.
+    pub fn create_texture(
+        &self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        format: PixelFormat,
+    ) -> std::result::Result<ImageTexture, Error> {
+        {
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
+            let texture = wlock.create_texture(width, height, data, format)?;
+            texture.set_owner(self.downgrade());
+            Ok(texture)
         }
     }
 
@@ -172,15 +772,132 @@ impl Overlay {
         color: &Color,
         alpha: f32,
     ) -> std::result::Result<VisualToken, Error> {
+        let options = DrawTextureOptions {
+            region: Some(*texture_region),
+            background: *color,
+            alpha,
+            ..DrawTextureOptions::new(
+                Rect::from(position.x, position.y)
+                    .sized(texture_region.width(), texture_region.height()),
+            )
+        };
+        self.draw_texture_with_options(texture, &options)
+    }
+
+    /// Draw a texture's region scaled into a destination rectangle, optionally rotated, flipped and tinted.
+    pub fn draw_texture_with_options(
+        &self,
+        texture: &ImageTexture,
+        options: &DrawTextureOptions,
+    ) -> std::result::Result<VisualToken, Error> {
+        let region = options
+            .region
+            .unwrap_or(Rect::from(0.0, 0.0).sized(texture.width() as f32, texture.height() as f32));
+        if region.width() <= 0.0 || region.height() <= 0.0 {
+            return Err("texture region must not be empty".into());
+        }
         {
-            let mut wlock = self.overlay.lock();
-            let visual = wlock.draw_texture(position, texture, texture_region, color, alpha)?;
-            Ok(VisualToken {
-                visual,
-                overlay: self.overlay.clone(),
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
+            let visual = wlock.draw_texture(texture, &[(region, *options)])?;
+            Ok(VisualToken::new(self, visual))
+        }
+    }
+
+    /// Draw a bordered texture region stretched over `destination` without distorting its border.
+    ///
+    /// The `insets` divide the source region into nine parts, the corners are drawn unscaled, the edges are
+    /// stretched along their length and the center fills the remainder. If the destination is smaller than the
+    /// corners they are shrunk to fit.
+    pub fn draw_texture_nine_slice(
+        &self,
+        texture: &ImageTexture,
+        source: &Rect,
+        insets: &Insets,
+        destination: &Rect,
+        alpha: f32,
+    ) -> std::result::Result<VisualToken, Error> {
+        let parts: Vec<(Rect, DrawTextureOptions)> = nine_slice(source, insets, destination)
+            .into_iter()
+            .map(|(region, destination)| {
+                let options = DrawTextureOptions {
+                    region: Some(region),
+                    alpha,
+                    ..DrawTextureOptions::new(destination)
+                };
+                (region, options)
             })
+            .collect();
+        {
+            let guard = self.overlay.lock();
+            let mut wlock = guard.borrow_mut();
+            let visual = wlock.draw_texture(texture, &parts)?;
+            Ok(VisualToken::new(self, visual))
+        }
+    }
+}
+
+/// Split `source` and `destination` into the nine matching parts, empty parts are left out.
+fn nine_slice(source: &Rect, insets: &Insets, destination: &Rect) -> Vec<(Rect, Rect)> {
+    // Shrink the corners proportionally if they don't fit in the destination.
+    let fit = |a: f32, b: f32, available: f32| {
+        let total = a + b;
+        if total > available && total > 0.0 {
+            let scale = available.max(0.0) / total;
+            (a * scale, b * scale)
+        } else {
+            (a, b)
+        }
+    };
+    let (left, right) = fit(insets.left, insets.right, destination.width());
+    let (top, bottom) = fit(insets.top, insets.bottom, destination.height());
+
+    let source_x = [
+        source.min.x,
+        source.min.x + insets.left,
+        source.max.x - insets.right,
+        source.max.x,
+    ];
+    let source_y = [
+        source.min.y,
+        source.min.y + insets.top,
+        source.max.y - insets.bottom,
+        source.max.y,
+    ];
+    let dest_x = [
+        destination.min.x,
+        destination.min.x + left,
+        destination.max.x - right,
+        destination.max.x,
+    ];
+    let dest_y = [
+        destination.min.y,
+        destination.min.y + top,
+        destination.max.y - bottom,
+        destination.max.y,
+    ];
+
+    let mut parts = vec![];
+    for row in 0..3 {
+        for column in 0..3 {
+            let region = Rect::new(
+                Point::new(source_x[column], source_y[row]),
+                Point::new(source_x[column + 1], source_y[row + 1]),
+            );
+            let target = Rect::new(
+                Point::new(dest_x[column], dest_y[row]),
+                Point::new(dest_x[column + 1], dest_y[row + 1]),
+            );
+            if region.width() > 0.0
+                && region.height() > 0.0
+                && target.width() > 0.0
+                && target.height() > 0.0
+            {
+                parts.push((region, target));
+            }
         }
     }
+    parts
 }
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -198,6 +915,7 @@ pub enum TextAlignment {
 
 /// Properties for the font and text.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TextProperties {
     /// The font family name, on windows defaults to 'Arial'.
     pub font: String,
@@ -223,51 +941,160 @@ impl Default for TextProperties {
     }
 }
 
-/// Color representation with alpha.
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Color {
-    pub r: u8,
-    pub g: u8,
-    pub b: u8,
-    /// Alpha channel, 255 is opaque, 0 is transparent.
-    pub a: u8,
+/// The layout of pixels in memory, all formats use 4 bytes per pixel.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum PixelFormat {
+    /// Red, green, blue, alpha, with straight alpha.
+    #[default]
+    Rgba8,
+    /// Blue, green, red, alpha, with straight alpha.
+    Bgra8,
+    /// Red, green, blue, alpha, with the colors multiplied by alpha.
+    Rgba8Premultiplied,
+    /// Blue, green, red, alpha, with the colors multiplied by alpha.
+    Bgra8Premultiplied,
 }
-impl Color {
-    pub const TRANSPARENT: Color = Color {
-        r: 0,
-        g: 0,
-        b: 0,
-        a: 0,
-    };
-    pub fn transparent(&self) -> Self {
-        Color {
-            r: self.r,
-            g: self.g,
-            b: self.b,
-            a: 0,
+impl PixelFormat {
+    /// Convert pixels into premultiplied blue, green, red, alpha; the format both backends use internally.
+    pub(crate) fn to_premultiplied_bgra(
+        self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+    ) -> std::result::Result<Vec<u8>, Error> {
+        if data.len() != width as usize * height as usize * 4 {
+            return Err(format!(
+                "texture data of {} bytes does not match {width}x{height} pixels",
+                data.len()
+            )
+            .into());
         }
+        let (swap, premultiply) = match self {
+            PixelFormat::Rgba8 => (true, true),
+            PixelFormat::Bgra8 => (false, true),
+            PixelFormat::Rgba8Premultiplied => (true, false),
+            PixelFormat::Bgra8Premultiplied => (false, false),
+        };
+        let mut pixels = data.to_vec();
+        for p in pixels.chunks_exact_mut(4) {
+            if swap {
+                p.swap(0, 2);
+            }
+            if premultiply {
+                let c = Color::rgba(p[0], p[1], p[2], p[3]).premultiplied();
+                p.copy_from_slice(&[c.r, c.g, c.b, c.a]);
+            }
+        }
+        Ok(pixels)
     }
-    pub fn a_f32(&self) -> f32 {
-        self.a as f32 / 255.0
-    }
-    pub fn r_f32(&self) -> f32 {
-        self.r as f32 / 255.0
+}
+
+/// How texture pixels are sampled when a texture is drawn scaled or rotated.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Use the closest pixel, keeps pixel art sharp.
+    Nearest,
+    /// Blend between neighbouring pixels.
+    #[default]
+    Linear,
+}
+
+/// Placement and appearance of a texture drawn with [`Overlay::draw_texture_with_options`].
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrawTextureOptions {
+    /// The area of the texture to be drawn, `None` draws the entire texture.
+    pub region: Option<Rect>,
+    /// The area on the screen, the region is scaled to fill it.
+    pub destination: Rect,
+    /// Rotation in degrees about the pivot, positive is clockwise.
+    pub rotation: f32,
+    /// Point to rotate about, relative to the destination; `(0.5, 0.5)` is its center.
+    pub pivot: Point,
+    /// Mirror the texture left to right.
+    pub flip_horizontal: bool,
+    /// Mirror the texture top to bottom.
+    pub flip_vertical: bool,
+    pub interpolation: Interpolation,
+    /// Multiplied with the texture's pixels, white draws the texture unchanged.
+    pub tint: Color,
+    /// The alpha at which the texture is drawn over the background, 0.0 is transparent, 1.0 is opaque.
+    pub alpha: f32,
+    /// Drawn underneath the texture, covering the destination.
+    pub background: Color,
+}
+impl Default for DrawTextureOptions {
+    fn default() -> Self {
+        Self {
+            region: None,
+            destination: Rect::new(Point::ORIGIN, Point::ORIGIN),
+            rotation: 0.0,
+            pivot: Point::new(0.5, 0.5),
+            flip_horizontal: false,
+            flip_vertical: false,
+            interpolation: Interpolation::default(),
+            tint: Color::WHITE,
+            alpha: 1.0,
+            background: Color::TRANSPARENT,
+        }
     }
-    pub fn g_f32(&self) -> f32 {
-        self.g as f32 / 255.0
+}
+impl DrawTextureOptions {
+    pub fn new(destination: Rect) -> Self {
+        Self {
+            destination,
+            ..Default::default()
+        }
     }
-    pub fn b_f32(&self) -> f32 {
-        self.b as f32 / 255.0
+
+    /// The transform from texture pixels in `region` to screen coordinates.
+    pub(crate) fn transform(&self, region: &Rect) -> Transform2D {
+        let dest = &self.destination;
+        let (mut sx, mut sy) = (
+            dest.width() / region.width(),
+            dest.height() / region.height(),
+        );
+        let mut offset = dest.min;
+        if self.flip_horizontal {
+            sx = -sx;
+            offset.x += dest.width();
+        }
+        if self.flip_vertical {
+            sy = -sy;
+            offset.y += dest.height();
+        }
+        let pivot = dest.anchor(self.pivot.x, self.pivot.y);
+        Transform2D::translation(-region.min.x, -region.min.y)
+            .then(&Transform2D::scale(sx, sy))
+            .then(&Transform2D::translation(offset.x, offset.y))
+            .then(&Transform2D::rotation_about(self.rotation, &pivot))
     }
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Self {
-            r: 0,
-            g: 0,
-            b: 0,
-            a: 255,
+/// Encoded image formats that can be loaded as textures.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Gif,
+    WebP,
+}
+impl ImageFormat {
+    /// Detect the format from the signature at the start of the data.
+    pub fn detect(data: &[u8]) -> Option<ImageFormat> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if data.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(ImageFormat::Jpeg)
+        } else if data.starts_with(b"BM") {
+            Some(ImageFormat::Bmp)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            Some(ImageFormat::WebP)
+        } else {
+            None
         }
     }
 }
@@ -325,10 +1152,32 @@ pub struct Point {
     pub y: f32,
 }
 impl Point {
-    const ORIGIN: Point = Point { x: 0.0, y: 0.0 };
+    pub const ORIGIN: Point = Point { x: 0.0, y: 0.0 };
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+    /// Length of the vector from the origin to this point.
+    pub fn length(&self) -> f32 {
+        self.x.hypot(self.y)
+    }
+    /// Scaled to unit length, a zero length vector is returned as is.
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return *self;
+        }
+        *self * (1.0 / length)
+    }
+    /// Linear interpolation, `t` of 0.0 returns `self`, 1.0 returns `other`.
+    pub fn lerp(&self, other: &Point, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+    /// Rotate about `center` by `degrees`, positive is clockwise on the screen.
+    pub fn rotate_about(&self, center: &Point, degrees: f32) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let d = *self - *center;
+        *center + Point::new(d.x * cos - d.y * sin, d.x * sin + d.y * cos)
+    }
 }
 impl std::ops::Add<Point> for Point {
     type Output = Point;
@@ -339,6 +1188,56 @@ impl std::ops::Add<Point> for Point {
         }
     }
 }
+impl std::ops::Sub<Point> for Point {
+    type Output = Point;
+    fn sub(self, other: Point) -> Point {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+impl std::ops::Mul<f32> for Point {
+    type Output = Point;
+    fn mul(self, scale: f32) -> Point {
+        Point {
+            x: self.x * scale,
+            y: self.y * scale,
+        }
+    }
+}
+impl std::ops::Neg for Point {
+    type Output = Point;
+    fn neg(self) -> Point {
+        Point {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+/// Distances inward from each side of a rectangle.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Insets {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+impl Insets {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+    /// The same distance on all sides.
+    pub fn uniform(amount: f32) -> Self {
+        Self::new(amount, amount, amount, amount)
+    }
+}
 
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Rect {
@@ -346,6 +1245,12 @@ pub struct Rect {
     pub max: Point,
 }
 impl Rect {
+    pub fn new(min: Point, max: Point) -> Self {
+        Self { min, max }
+    }
+    /// Start a rectangle at `(x, y)`, to be followed by [`Rect::sized`].
+    ///
+    /// Note that on its own this sets `max` to the origin.
     pub fn from(x: f32, y: f32) -> Self {
         Self {
             min: Point { x, y },
@@ -358,12 +1263,75 @@ impl Rect {
             max: self.min + Point::new(w, h),
         }
     }
+    /// Rectangle of the provided size, centered on `center`.
+    pub fn from_center_size(center: &Point, w: f32, h: f32) -> Self {
+        let half = Point::new(w / 2.0, h / 2.0);
+        Self {
+            min: *center - half,
+            max: *center + half,
+        }
+    }
     pub fn width(&self) -> f32 {
         self.max.x - self.min.x
     }
     pub fn height(&self) -> f32 {
         self.max.y - self.min.y
     }
+    pub fn center(&self) -> Point {
+        self.min.lerp(&self.max, 0.5)
+    }
+    /// A point relative to the rectangle, `(0.0, 0.0)` is `min`, `(1.0, 1.0)` is `max`.
+    pub fn anchor(&self, x: f32, y: f32) -> Point {
+        Point::new(
+            self.min.x + self.width() * x,
+            self.min.y + self.height() * y,
+        )
+    }
+    /// Returns true if the point is inside, `min` is inclusive, `max` is exclusive.
+    pub fn contains(&self, p: &Point) -> bool {
+        p.x >= self.min.x && p.x < self.max.x && p.y >= self.min.y && p.y < self.max.y
+    }
+    /// The overlapping area, `None` if the rectangles don't overlap.
+    pub fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let r = Rect {
+            min: Point::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y)),
+            max: Point::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y)),
+        };
+        (r.width() > 0.0 && r.height() > 0.0).then_some(r)
+    }
+    /// The smallest rectangle that contains both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min: Point::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Point::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
+    /// The axis aligned bounds of this rectangle after transforming its corners.
+    pub fn transformed_bounds(&self, transform: &Transform2D) -> Rect {
+        let corners = [
+            self.min,
+            Point::new(self.max.x, self.min.y),
+            self.max,
+            Point::new(self.min.x, self.max.y),
+        ]
+        .map(|p| transform.transform_point(&p));
+        let start = Rect::new(corners[0], corners[0]);
+        corners[1..]
+            .iter()
+            .fold(start, |r, p| r.union(&Rect::new(*p, *p)))
+    }
+    /// Shrink by `amount` on all sides.
+    pub fn inset(&self, amount: f32) -> Rect {
+        self.expand(-amount)
+    }
+    /// Grow by `amount` on all sides.
+    pub fn expand(&self, amount: f32) -> Rect {
+        let d = Point::new(amount, amount);
+        Rect {
+            min: self.min - d,
+            max: self.max + d,
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
@@ -395,6 +1363,11 @@ pub enum GeometryElement {
 pub struct DrawGeometry {
     pub elements: Vec<GeometryElement>,
 }
+impl Default for DrawGeometry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl DrawGeometry {
     pub fn new() -> Self {
         Self { elements: vec![] }
@@ -461,3 +1434,237 @@ impl DrawGeometry {
         .closed()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: &Point, b: &Point) -> bool {
+        (*a - *b).length() < 1e-4
+    }
+
+    #[test]
+    fn test_point_ops() {
+        let a = Point::new(1.0, 2.0);
+        let b = Point::new(4.0, 6.0);
+        assert_eq!(a + b, Point::new(5.0, 8.0));
+        assert_eq!(b - a, Point::new(3.0, 4.0));
+        assert_eq!(a * 2.0, Point::new(2.0, 4.0));
+        assert_eq!(-a, Point::new(-1.0, -2.0));
+        assert_eq!((b - a).length(), 5.0);
+    }
+
+    #[test]
+    fn test_point_normalize() {
+        assert!(approx(
+            &Point::new(3.0, 4.0).normalize(),
+            &Point::new(0.6, 0.8)
+        ));
+        assert_eq!(Point::ORIGIN.normalize(), Point::ORIGIN);
+    }
+
+    #[test]
+    fn test_point_lerp() {
+        let a = Point::new(0.0, 10.0);
+        let b = Point::new(10.0, 20.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.25), Point::new(2.5, 12.5));
+    }
+
+    #[test]
+    fn test_point_rotate_about() {
+        let center = Point::new(1.0, 1.0);
+        let p = Point::new(2.0, 1.0);
+        assert!(approx(
+            &p.rotate_about(&center, 90.0),
+            &Point::new(1.0, 2.0)
+        ));
+        assert!(approx(
+            &p.rotate_about(&center, -90.0),
+            &Point::new(1.0, 0.0)
+        ));
+        assert!(approx(
+            &p.rotate_about(&center, 180.0),
+            &Point::new(0.0, 1.0)
+        ));
+    }
+
+    #[test]
+    fn test_rect_construction() {
+        let r = Rect::from(10.0, 20.0).sized(30.0, 40.0);
+        assert_eq!(r, Rect::new(Point::new(10.0, 20.0), Point::new(40.0, 60.0)));
+        assert_eq!(r.width(), 30.0);
+        assert_eq!(r.height(), 40.0);
+        assert_eq!(r.center(), Point::new(25.0, 40.0));
+        assert_eq!(
+            Rect::from_center_size(&Point::new(25.0, 40.0), 30.0, 40.0),
+            r
+        );
+    }
+
+    #[test]
+    fn test_rect_anchor() {
+        let r = Rect::from(10.0, 20.0).sized(30.0, 40.0);
+        assert_eq!(r.anchor(0.0, 0.0), r.min);
+        assert_eq!(r.anchor(1.0, 1.0), r.max);
+        assert_eq!(r.anchor(0.5, 0.5), r.center());
+        assert_eq!(r.anchor(1.0, 0.0), Point::new(40.0, 20.0));
+    }
+
+    #[test]
+    fn test_rect_contains() {
+        let r = Rect::from(0.0, 0.0).sized(10.0, 10.0);
+        assert!(r.contains(&Point::new(0.0, 0.0)));
+        assert!(r.contains(&Point::new(5.0, 9.9)));
+        assert!(!r.contains(&Point::new(10.0, 5.0)));
+        assert!(!r.contains(&Point::new(-1.0, 5.0)));
+    }
+
+    #[test]
+    fn test_rect_intersect_union() {
+        let a = Rect::from(0.0, 0.0).sized(10.0, 10.0);
+        let b = Rect::from(5.0, 5.0).sized(10.0, 10.0);
+        assert_eq!(a.intersect(&b), Some(Rect::from(5.0, 5.0).sized(5.0, 5.0)));
+        assert_eq!(a.union(&b), Rect::from(0.0, 0.0).sized(15.0, 15.0));
+
+        let c = Rect::from(10.0, 0.0).sized(10.0, 10.0);
+        assert_eq!(a.intersect(&c), None);
+        assert_eq!(a.union(&c), Rect::from(0.0, 0.0).sized(20.0, 10.0));
+    }
+
+    #[test]
+    fn test_pixel_format_conversion() {
+        let rgba = [255, 128, 0, 128];
+        let bgra = PixelFormat::Rgba8
+            .to_premultiplied_bgra(1, 1, &rgba)
+            .unwrap();
+        assert_eq!(bgra, [0, 64, 128, 128]);
+        let bgra = PixelFormat::Bgra8Premultiplied
+            .to_premultiplied_bgra(1, 1, &rgba)
+            .unwrap();
+        assert_eq!(bgra, rgba);
+        assert!(PixelFormat::Rgba8
+            .to_premultiplied_bgra(2, 1, &rgba)
+            .is_err());
+    }
+
+    #[test]
+    fn test_draw_texture_options_transform() {
+        let region = Rect::from(10.0, 20.0).sized(16.0, 8.0);
+        let mut options = DrawTextureOptions::new(Rect::from(100.0, 100.0).sized(32.0, 16.0));
+        let t = options.transform(&region);
+        assert_eq!(t.transform_point(&region.min), Point::new(100.0, 100.0));
+        assert_eq!(t.transform_point(&region.max), Point::new(132.0, 116.0));
+
+        options.flip_horizontal = true;
+        let t = options.transform(&region);
+        assert_eq!(t.transform_point(&region.min), Point::new(132.0, 100.0));
+
+        options.flip_horizontal = false;
+        options.rotation = 90.0;
+        let bounds = region.transformed_bounds(&options.transform(&region));
+        let near = |a: Point, b: Point| (a - b).length() < 1e-3;
+        assert!(near(bounds.min, Point::new(108.0, 92.0)));
+        assert!(near(bounds.max, Point::new(124.0, 124.0)));
+        let t = options.transform(&region);
+        assert!(near(
+            t.transform_point(&region.min),
+            Point::new(124.0, 92.0)
+        ));
+    }
+
+    #[test]
+    fn test_placement_transform() {
+        let placement = Placement {
+            position: Point::new(5.0, 0.0),
+            rotation: 90.0,
+            pivot: Point::new(10.0, 10.0),
+            ..Default::default()
+        };
+        let p = placement
+            .transform()
+            .transform_point(&Point::new(20.0, 10.0));
+        assert!((p - Point::new(15.0, 20.0)).length() < 1e-4);
+        // Scaling about the pivot keeps the pivot in place.
+        let placement = Placement {
+            scale: 2.0,
+            ..placement
+        };
+        let p = placement
+            .transform()
+            .transform_point(&Point::new(10.0, 10.0));
+        assert!((p - Point::new(15.0, 10.0)).length() < 1e-4);
+    }
+
+    #[test]
+    fn test_nine_slice() {
+        let source = Rect::from(0.0, 0.0).sized(30.0, 30.0);
+        let destination = Rect::from(100.0, 100.0).sized(200.0, 50.0);
+        let parts = nine_slice(&source, &Insets::uniform(10.0), &destination);
+        assert_eq!(parts.len(), 9);
+        // Corners are unscaled.
+        assert_eq!(parts[0].0, Rect::from(0.0, 0.0).sized(10.0, 10.0));
+        assert_eq!(parts[0].1, Rect::from(100.0, 100.0).sized(10.0, 10.0));
+        assert_eq!(parts[8].1, Rect::from(290.0, 140.0).sized(10.0, 10.0));
+        // Top edge stretches horizontally only, the center fills the rest.
+        assert_eq!(parts[1].1, Rect::from(110.0, 100.0).sized(180.0, 10.0));
+        assert_eq!(parts[4].0, Rect::from(10.0, 10.0).sized(10.0, 10.0));
+        assert_eq!(parts[4].1, Rect::from(110.0, 110.0).sized(180.0, 30.0));
+
+        // Corners shrink when the destination is too small, the empty middle parts are dropped.
+        let small = Rect::from(0.0, 0.0).sized(10.0, 10.0);
+        let parts = nine_slice(&source, &Insets::uniform(10.0), &small);
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[3].1, Rect::from(5.0, 5.0).sized(5.0, 5.0));
+
+        // Zero insets draw the region as a whole.
+        let parts = nine_slice(&source, &Insets::default(), &destination);
+        assert_eq!(parts, vec![(source, destination)]);
+    }
+
+    #[test]
+    fn test_image_format_detect() {
+        assert_eq!(
+            ImageFormat::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::detect(&[0xff, 0xd8, 0xff, 0xe0]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(ImageFormat::detect(b"BM6\0\0\0"), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::detect(b"GIF89a\x01\0"), Some(ImageFormat::Gif));
+        assert_eq!(
+            ImageFormat::detect(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(ImageFormat::detect(b"RIFF\x24\0\0\0WAVE"), None);
+        assert_eq!(ImageFormat::detect(b"<svg"), None);
+    }
+
+    #[test]
+    fn test_rect_inset_expand() {
+        let r = Rect::from(10.0, 10.0).sized(20.0, 20.0);
+        assert_eq!(r.inset(2.0), Rect::from(12.0, 12.0).sized(16.0, 16.0));
+        assert_eq!(r.expand(2.0), Rect::from(8.0, 8.0).sized(24.0, 24.0));
+        assert_eq!(r.inset(2.0).expand(2.0), r);
+    }
+
+    #[test]
+    fn test_fade_opacity() {
+        let ms = Duration::from_millis;
+        // Untouched until the last second, then linearly to nothing.
+        assert_eq!(fade_opacity(0.8, ms(0), ms(3000), ms(1000)), 0.8);
+        assert_eq!(fade_opacity(0.8, ms(2000), ms(3000), ms(1000)), 0.8);
+        assert!((fade_opacity(0.8, ms(2500), ms(3000), ms(1000)) - 0.4).abs() < 1e-6);
+        assert_eq!(fade_opacity(0.8, ms(3000), ms(3000), ms(1000)), 0.0);
+        assert_eq!(fade_opacity(0.8, ms(5000), ms(3000), ms(1000)), 0.0);
+        // Without a fade it stays until the end.
+        assert_eq!(fade_opacity(1.0, ms(2999), ms(3000), ms(0)), 1.0);
+        assert_eq!(fade_opacity(1.0, ms(3000), ms(3000), ms(0)), 0.0);
+        // A fade longer than the duration starts right away.
+        assert_eq!(fade_opacity(1.0, ms(0), ms(1000), ms(4000)), 1.0);
+        assert!((fade_opacity(1.0, ms(250), ms(1000), ms(4000)) - 0.75).abs() < 1e-6);
+    }
+}
@@ -1,77 +1,210 @@
 #![allow(unused_variables, unused_imports, unreachable_code)]
 use crate::{
-    CapStyle, CircleDirection, Color, DashStyle, DrawGeometry, Error, GeometryElement, LineJoin,
-    LineStyle, OverlayConfig, Point, Rect, Stroke, TextAlignment, TextProperties,
+    CapStyle, CircleDirection, Color, DashStyle, DrawGeometry, DrawTextureOptions, Error,
+    GeometryElement, ImageFormat, Interpolation, LineJoin, LineStyle, Monitor, OverlayConfig,
+    PixelFormat, Point, Rect, Stroke, TextAlignment, TextProperties, Transform2D,
 };
 
 /*
     We can probably draw on https://github.com/ftorkler/x11-overlay for a lot of the logic.
 */
 
-use x11_dl::xlib::{self, TrueColor, Xlib, _XDisplay};
-use x11_dl::{xfixes, xft, xrender};
+use x11_dl::xlib::{self, _XDisplay, TrueColor, Xlib};
+use x11_dl::{xfixes, xft, xinerama, xrender};
 
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
+use crate::tree::{Appearance, Composed, Node, Tree};
+
+struct TextureInner {
+    display: *mut _XDisplay,
+    visual: *mut xlib::Visual,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    pixmap: xlib::Pixmap,
+    picture: xrender::Picture,
+    /// The overlay the texture was created by, its visuals are redrawn when the texture is updated.
+    owner: OnceLock<crate::WeakOverlay>,
+}
+// Xlib is initialised for use from multiple threads in `setup`.
+unsafe impl Send for TextureInner {}
+unsafe impl Sync for TextureInner {}
+impl Drop for TextureInner {
+    fn drop(&mut self) {
+        unsafe {
+            let (Ok(instance), Ok(render)) = (xlib::Xlib::open(), xrender::Xrender::open()) else {
+                return; // how can we handle this? return of drop is void.
+            };
+            (render.XRenderFreePicture)(self.display, self.picture);
+            (instance.XFreePixmap)(self.display, self.pixmap);
+        }
+    }
+}
+
+/// A texture, backed by an XRender picture of a 32 bit pixmap.
 #[derive(Clone)]
-pub struct ImageTexture {}
+pub struct ImageTexture {
+    inner: Arc<TextureInner>,
+}
 impl std::fmt::Debug for ImageTexture {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "ImageTexture {:?}", &self)
+        write!(
+            f,
+            "ImageTexture {}x{} {:?}",
+            self.inner.width, self.inner.height, self.inner.picture
+        )
+    }
+}
+impl ImageTexture {
+    pub fn width(&self) -> u32 {
+        self.inner.width
+    }
+    pub fn height(&self) -> u32 {
+        self.inner.height
+    }
+    pub fn downgrade(&self) -> WeakImageTexture {
+        WeakImageTexture {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+    /// Replace the pixels, `data` must be of the size and format the texture was created with.
+    ///
+    /// Visuals already drawn with the texture are redrawn to show the new contents.
+    pub fn update(&self, data: &[u8]) -> Result<(), Error> {
+        let inner = &self.inner;
+        let pixels = inner
+            .format
+            .to_premultiplied_bgra(inner.width, inner.height, data)?;
+        let owner = inner.owner.get().and_then(|o| o.upgrade());
+        let guard = owner.as_ref().map(|o| o.overlay.lock());
+        unsafe {
+            put_pixels(
+                inner.display,
+                inner.visual,
+                inner.pixmap,
+                inner.width,
+                inner.height,
+                &pixels,
+            )?;
+        }
+        if let Some(guard) = guard {
+            guard.borrow_mut().refresh_texture(self)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn set_owner(&self, overlay: crate::WeakOverlay) {
+        let _ = self.inner.owner.set(overlay);
     }
 }
 
+/// A texture reference that doesn't keep the texture alive.
 #[derive(Clone)]
-pub struct PreparedFont {
-    display: *mut _XDisplay,
-    font: *mut xft::XftFont,
+pub struct WeakImageTexture {
+    inner: std::sync::Weak<TextureInner>,
 }
-impl std::fmt::Debug for PreparedFont {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "PreparedFont {:?}", &self)
+impl WeakImageTexture {
+    pub fn upgrade(&self) -> Option<ImageTexture> {
+        self.inner.upgrade().map(|inner| ImageTexture { inner })
+    }
+}
+
+/// Upload premultiplied BGRA pixels into a 32 bit pixmap.
+unsafe fn put_pixels(
+    display: *mut _XDisplay,
+    visual: *mut xlib::Visual,
+    pixmap: xlib::Pixmap,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), Error> {
+    let instance = xlib::Xlib::open()?;
+    let image = (instance.XCreateImage)(
+        display,
+        visual,
+        32,
+        xlib::ZPixmap,
+        0,
+        pixels.as_ptr() as *mut _,
+        width,
+        height,
+        32,
+        (width * 4) as i32,
+    );
+    if image.is_null() {
+        return Err("failed to create image".into());
     }
+    let gc = (instance.XCreateGC)(display, pixmap, 0, std::ptr::null_mut());
+    (instance.XPutImage)(display, pixmap, gc, image, 0, 0, 0, 0, width, height);
+    (instance.XFreeGC)(display, gc);
+    // The pixel data is ours, so only free the image structure, XDestroyImage would free the data as well.
+    (instance.XFree)(image as *mut _);
+    Ok(())
 }
-impl Drop for PreparedFont {
+
+struct FontInner {
+    display: *mut _XDisplay,
+    font: *mut xft::XftFont,
+}
+unsafe impl Send for FontInner {}
+unsafe impl Sync for FontInner {}
+impl Drop for FontInner {
     fn drop(&mut self) {
         unsafe {
             let xft = xft::Xft::open();
             if xft.is_err() {
                 return; // how can we handle this? return of drop is void.
             }
-            let font = (xft.unwrap().XftFontClose)(self.display, self.font);
+            (xft.unwrap().XftFontClose)(self.display, self.font);
         }
     }
 }
 
-// pub type IDVisual = usize;
-#[derive(Clone, Debug)]
-pub enum IDVisual {
-    Text { xft_draw: *mut xft::XftDraw },
-    None,
+#[derive(Clone)]
+pub struct PreparedFont {
+    inner: Arc<FontInner>,
 }
-impl Drop for IDVisual {
-    fn drop(&mut self) {
-        unsafe {
-            match self {
-                IDVisual::Text { xft_draw } => {
-                    let xft = xft::Xft::open();
-                    if xft.is_err() {
-                        return; // how can we handle this? return of drop is void.
-                    }
-                    let font = (xft.unwrap().XftDrawDestroy)(*xft_draw);
-                }
-                IDVisual::None => {}
-            }
-        }
+impl std::fmt::Debug for PreparedFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "PreparedFont {:?}", self.inner.font)
     }
 }
 
+/// Geometry isn't rendered on X11 yet, `draw_geometry` hands out visuals without content.
+pub const DRAWS_GEOMETRY: bool = false;
+
+/// Handle to a visual, the content itself is held by the overlay's visual tree.
+pub use crate::tree::NodeId as IDVisual;
+
+/// Everything needed to render a visual, the window is redrawn from these whenever something changes.
+enum Content {
+    Text {
+        text: String,
+        layout: Rect,
+        color: Color,
+        font: PreparedFont,
+    },
+    Texture {
+        texture: ImageTexture,
+        parts: Vec<(Rect, DrawTextureOptions)>,
+    },
+}
+
 pub struct OverlayImpl {
     instance: Xlib,
     display: *mut _XDisplay,
     screen: Option<i32>,
     window: Option<u64>,
     visual_info: Option<xlib::XVisualInfo>,
+    colormap: Option<xlib::Colormap>,
+    xft_draw: Option<*mut xft::XftDraw>,
+    picture: Option<xrender::Picture>,
+    /// Groups and visuals without content hold `None`.
+    tree: Tree<Option<Content>>,
+    /// Number of open batches, the window isn't redrawn while this is non zero.
+    batch: usize,
+    /// Set if a redraw was skipped because of an open batch.
+    pending_redraw: bool,
 }
 unsafe impl Send for OverlayImpl {}
 
@@ -88,6 +221,12 @@ impl OverlayImpl {
             screen: None,
             window: None,
             visual_info: None,
+            colormap: None,
+            xft_draw: None,
+            picture: None,
+            tree: Tree::new(None),
+            batch: 0,
+            pending_redraw: false,
         })
     }
 
@@ -113,7 +252,7 @@ impl OverlayImpl {
 
             let status = (self.instance.XMatchVisualInfo)(
                 self.display as _,
-                screen as i32,
+                screen,
                 32,
                 xlib::TrueColor,
                 visual_info.as_mut_ptr(),
@@ -170,9 +309,98 @@ impl OverlayImpl {
             (xlib_fixes.XFixesSetWindowShapeRegion)(self.display, window, ShapeInput, 0, 0, region);
             (xlib_fixes.XFixesDestroyRegion)(self.display, region);
             (self.instance.XMapWindow)(self.display, window);
+
+            let xft = xft::Xft::open()?;
+            let xft_draw = (xft.XftDrawCreate)(
+                self.display,
+                window,
+                visual_info.visual,
+                attributes.colormap,
+            );
+            let render = xrender::Xrender::open()?;
+            let format = (render.XRenderFindVisualFormat)(self.display, visual_info.visual);
+            let picture =
+                (render.XRenderCreatePicture)(self.display, window, format, 0, std::ptr::null());
+
             self.window = Some(window);
             self.visual_info = Some(visual_info);
             self.screen = Some(screen);
+            self.colormap = Some(attributes.colormap);
+            self.xft_draw = Some(xft_draw);
+            self.picture = Some(picture);
+        }
+        Ok(())
+    }
+
+    fn add_visual(&mut self, content: Option<Content>) -> Result<IDVisual, Error> {
+        let id = self.tree.insert(IDVisual::ROOT, content)?;
+        if self.tree.get(id).is_some_and(|n| n.data.is_some()) {
+            self.redraw()?;
+        }
+        Ok(id)
+    }
+
+    /// Defer redraws until the matching [`OverlayImpl::end_batch`], batches can be nested.
+    pub fn begin_batch(&mut self) {
+        self.batch += 1;
+    }
+
+    /// Close a batch, the window is redrawn once if anything changed while the last batch was open.
+    pub fn end_batch(&mut self) -> Result<(), Error> {
+        self.batch = self.batch.saturating_sub(1);
+        if self.batch == 0 && std::mem::take(&mut self.pending_redraw) {
+            self.redraw()?;
+        }
+        Ok(())
+    }
+
+    fn redraw(&mut self) -> Result<(), Error> {
+        if self.batch > 0 {
+            self.pending_redraw = true;
+            return Ok(());
+        }
+        self.render()
+    }
+
+    /// Clear the window and render all visuals again.
+    fn render(&self) -> Result<(), Error> {
+        let window = self.window.ok_or("redraw called without window created")?;
+        unsafe {
+            (self.instance.XClearWindow)(self.display, window);
+            let mut result = Ok(());
+            self.tree.walk(|_, node, composed| {
+                let Composed {
+                    transform,
+                    opacity,
+                    tint,
+                } = composed;
+                let rendered = match &node.data {
+                    None => Ok(()),
+                    Some(Content::Text {
+                        text,
+                        layout,
+                        color,
+                        font,
+                    }) => {
+                        let color = color.multiply(tint);
+                        self.render_text(text, layout, &color, font, transform, *opacity)
+                    }
+                    Some(Content::Texture { texture, parts }) => {
+                        parts.iter().try_for_each(|(region, options)| {
+                            let options = DrawTextureOptions {
+                                tint: options.tint.multiply(tint),
+                                ..*options
+                            };
+                            self.render_texture(texture, region, &options, transform, *opacity)
+                        })
+                    }
+                };
+                if result.is_ok() {
+                    result = rendered;
+                }
+            });
+            (self.instance.XFlush)(self.display);
+            result?;
         }
         Ok(())
     }
@@ -187,7 +415,8 @@ impl OverlayImpl {
         stroke: &Stroke,
         line_style: &LineStyle,
     ) -> Result<IDVisual, Error> {
-        Ok(IDVisual::None)
+        // Geometry isn't supported on X11 yet, hand out a visual without content.
+        self.add_visual(None)
     }
 
     pub fn prepare_font(&mut self, properties: &TextProperties) -> Result<PreparedFont, Error> {
@@ -195,19 +424,21 @@ impl OverlayImpl {
             let xft = xft::Xft::open()?;
             let font_descriptor =
                 format!("{}:pixelsize={}", properties.font, properties.size as i32);
-            let font_name = std::ffi::OsString::from(&font_descriptor);
-            let font_str = std::mem::transmute::<*const u8, _>(
-                font_name.as_os_str().as_encoded_bytes().as_ptr(),
-            );
+            let font_name = std::ffi::CString::new(font_descriptor)?;
             let font = (xft.XftFontOpenName)(
                 self.display,
                 *self.screen.as_ref().ok_or("no screen")?,
-                font_str,
+                font_name.as_ptr(),
             );
+            if font.is_null() {
+                return Err("failed to open font".into());
+            }
             // println!("font prop: {font:?}");
             Ok(PreparedFont {
-                display: self.display,
-                font,
+                inner: Arc::new(FontInner {
+                    display: self.display,
+                    font,
+                }),
             })
         }
     }
@@ -219,93 +450,433 @@ impl OverlayImpl {
         color: &Color,
         font: &PreparedFont,
     ) -> Result<IDVisual, Error> {
-        // println!("would print {text}");
-        unsafe {
-            let xft = xft::Xft::open()?;
-            let screen = *self
-                .screen
-                .as_ref()
-                .ok_or("draw_text called without screen created")?;
-            let window = *self
-                .window
-                .as_ref()
-                .ok_or("draw_text called without window created")?;
-
-            // This is a bit of a hack.
-            (self.instance.XClearWindow)(self.display, window);
+        self.add_visual(Some(Content::Text {
+            text: text.to_owned(),
+            layout: *layout,
+            color: *color,
+            font: font.clone(),
+        }))
+    }
 
-            let visual_info = *self
-                .visual_info
-                .as_ref()
-                .ok_or("draw_text called without window created")?;
-            let colormap = (self.instance.XDefaultColormap)(self.display, screen);
-            let xft_draw = (xft.XftDrawCreate)(self.display, window, visual_info.visual, colormap);
-            let mut xft_color: xft::XftColor = std::mem::MaybeUninit::zeroed().assume_init();
+    unsafe fn render_text(
+        &self,
+        text: &str,
+        layout: &Rect,
+        color: &Color,
+        font: &PreparedFont,
+        transform: &Transform2D,
+        opacity: f32,
+    ) -> Result<(), Error> {
+        let xft = xft::Xft::open()?;
+        let xft_draw = self
+            .xft_draw
+            .ok_or("draw_text called without window created")?;
+        let visual_info = *self
+            .visual_info
+            .as_ref()
+            .ok_or("draw_text called without window created")?;
+        let colormap = self
+            .colormap
+            .ok_or("draw_text called without window created")?;
+        let mut xft_color: xft::XftColor = std::mem::MaybeUninit::zeroed().assume_init();
 
-            let mut render_color: xrender::XRenderColor =
-                std::mem::MaybeUninit::zeroed().assume_init();
-            render_color.red = ((color.r_f32() * color.a_f32()) * 255.0) as u16 * 255;
-            render_color.green = ((color.g_f32() * color.a_f32()) * 255.0) as u16 * 255;
-            render_color.blue = ((color.b_f32() * color.a_f32()) * 255.0) as u16 * 255;
-            render_color.alpha = color.a as u16 * 255;
-            let status = (xft.XftColorAllocValue)(
-                self.display,
-                visual_info.visual,
-                colormap,
-                &render_color,
-                &mut xft_color,
-            );
-            if status == 0 {
-                return Err("could not allocate color".into());
-            }
+        let color = color.with_alpha((color.a as f32 * opacity).round() as u8);
+        let [red, green, blue, alpha] = color.to_premultiplied_u16();
+        let render_color = xrender::XRenderColor {
+            red,
+            green,
+            blue,
+            alpha,
+        };
+        let status = (xft.XftColorAllocValue)(
+            self.display,
+            visual_info.visual,
+            colormap,
+            &render_color,
+            &mut xft_color,
+        );
+        if status == 0 {
+            return Err("could not allocate color".into());
+        }
 
-            // XftDrawStringUtf8(xftDraw, &xftColor, xftFont, x, y + xftFont->ascent, (const FcChar8*)text.c_str(), text.size());
-            let x = layout.min.x as i32;
-            let y = layout.min.y as i32 + (*(font.font)).ascent;
-            // println!("x: {x}, y: {y}");
-            let b: Vec<u8> = text
-                .as_bytes()
-                .iter()
-                .chain([0u8].iter())
-                .copied()
-                .collect();
-            (xft.XftDrawStringUtf8)(
-                xft_draw,
-                &xft_color,
-                font.font,
-                x,
-                y,
-                b.as_ptr(),
-                b.len() as i32,
-            );
+        // XftDrawStringUtf8(xftDraw, &xftColor, xftFont, x, y + xftFont->ascent, (const FcChar8*)text.c_str(), text.size());
+        // Xft can't scale or rotate text, it only follows the translation.
+        let position = transform.transform_point(&layout.min);
+        let x = position.x as i32;
+        let y = position.y as i32 + (*(font.inner.font)).ascent;
+        // println!("x: {x}, y: {y}");
+        (xft.XftDrawStringUtf8)(
+            xft_draw,
+            &xft_color,
+            font.inner.font,
+            x,
+            y,
+            text.as_ptr(),
+            text.len() as i32,
+        );
 
-            (self.instance.XFlush)(self.display);
-            // XftDrawDestroy XftColorFree!
-            Ok(IDVisual::Text { xft_draw })
-        }
+        (xft.XftColorFree)(self.display, visual_info.visual, colormap, &mut xft_color);
+        Ok(())
     }
 
     pub fn load_texture<P: AsRef<std::path::Path>>(
         &mut self,
         path: P,
     ) -> Result<ImageTexture, Error> {
-        Ok(ImageTexture {})
+        let data = std::fs::read(path)?;
+        let format =
+            ImageFormat::detect(&data).ok_or("unsupported or unrecognised image format")?;
+        self.load_texture_from_bytes(&data, format)
+    }
+
+    pub fn load_texture_from_bytes(
+        &mut self,
+        data: &[u8],
+        format: ImageFormat,
+    ) -> Result<ImageTexture, Error> {
+        let format = match format {
+            ImageFormat::Png => image::ImageFormat::Png,
+            ImageFormat::Jpeg => image::ImageFormat::Jpeg,
+            ImageFormat::Bmp => image::ImageFormat::Bmp,
+            ImageFormat::Gif => image::ImageFormat::Gif,
+            ImageFormat::WebP => image::ImageFormat::WebP,
+        };
+        let decoded = image::load_from_memory_with_format(data, format)?.into_rgba8();
+        let (width, height) = decoded.dimensions();
+        self.create_texture(width, height, decoded.as_raw(), PixelFormat::Rgba8)
+    }
+
+    pub fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        format: PixelFormat,
+    ) -> Result<ImageTexture, Error> {
+        let pixels = format.to_premultiplied_bgra(width, height, data)?;
+        if width == 0 || height == 0 {
+            return Err("textures must be at least one pixel".into());
+        }
+        let window = self
+            .window
+            .ok_or("create_texture called without window created")?;
+        let visual_info = self
+            .visual_info
+            .ok_or("create_texture called without window created")?;
+        unsafe {
+            let render = xrender::Xrender::open()?;
+            let pixmap = (self.instance.XCreatePixmap)(self.display, window, width, height, 32);
+            let picture_format =
+                (render.XRenderFindStandardFormat)(self.display, xrender::PictStandardARGB32);
+            let picture = (render.XRenderCreatePicture)(
+                self.display,
+                pixmap,
+                picture_format,
+                0,
+                std::ptr::null(),
+            );
+            let texture = ImageTexture {
+                inner: Arc::new(TextureInner {
+                    display: self.display,
+                    visual: visual_info.visual,
+                    width,
+                    height,
+                    format,
+                    pixmap,
+                    picture,
+                    owner: OnceLock::new(),
+                }),
+            };
+            put_pixels(
+                self.display,
+                visual_info.visual,
+                pixmap,
+                width,
+                height,
+                &pixels,
+            )?;
+            Ok(texture)
+        }
     }
 
     pub fn draw_texture(
         &mut self,
-        position: &Point,
         texture: &ImageTexture,
-        texture_region: &Rect,
-        color: &Color,
-        alpha: f32,
+        parts: &[(Rect, DrawTextureOptions)],
     ) -> Result<IDVisual, Error> {
-        Ok(IDVisual::None)
+        self.add_visual(Some(Content::Texture {
+            texture: texture.clone(),
+            parts: parts.to_vec(),
+        }))
+    }
+
+    /// Redraw the window if a visual shows `texture`, after its pixels changed.
+    pub fn refresh_texture(&mut self, texture: &ImageTexture) -> Result<(), Error> {
+        let shown = self.tree.nodes().any(|node| {
+            matches!(&node.data, Some(Content::Texture { texture: drawn, .. })
+                if Arc::ptr_eq(&drawn.inner, &texture.inner))
+        });
+        if shown {
+            self.redraw()?;
+        }
+        Ok(())
+    }
+
+    /// Draws the texture in two steps; the background and the tinted region are first composed into a scratch
+    /// picture of the region's size, which is then composited onto the window through the inverse transform. This
+    /// keeps pixels outside the region from being sampled and lets the background rotate with the texture.
+    unsafe fn render_texture(
+        &self,
+        texture: &ImageTexture,
+        region: &Rect,
+        options: &DrawTextureOptions,
+        group_transform: &Transform2D,
+        group_opacity: f32,
+    ) -> Result<(), Error> {
+        let render = xrender::Xrender::open()?;
+        let picture = self
+            .picture
+            .ok_or("draw_texture called without window created")?;
+        let window = self
+            .window
+            .ok_or("draw_texture called without window created")?;
+        let (width, height) = (region.width().ceil() as u32, region.height().ceil() as u32);
+
+        let pixmap = (self.instance.XCreatePixmap)(self.display, window, width, height, 32);
+        let picture_format =
+            (render.XRenderFindStandardFormat)(self.display, xrender::PictStandardARGB32);
+        let scratch = (render.XRenderCreatePicture)(
+            self.display,
+            pixmap,
+            picture_format,
+            0,
+            std::ptr::null(),
+        );
+
+        let [red, green, blue, alpha] = options
+            .background
+            .to_premultiplied_u16()
+            .map(|v| (v as f32 * group_opacity).round() as u16);
+        let background = xrender::XRenderColor {
+            red,
+            green,
+            blue,
+            alpha,
+        };
+        (render.XRenderFillRectangle)(
+            self.display,
+            xrender::PictOpSrc,
+            scratch,
+            &background,
+            0,
+            0,
+            width,
+            height,
+        );
+
+        // With component alpha every channel of the texture is multiplied by the matching channel of the mask, the
+        // mask is the premultiplied tint scaled by the alpha.
+        let opacity = options.alpha.clamp(0.0, 1.0) * group_opacity;
+        let [red, green, blue, alpha] = options
+            .tint
+            .to_premultiplied_u16()
+            .map(|v| (v as f32 * opacity).round() as u16);
+        let mask_color = xrender::XRenderColor {
+            red,
+            green,
+            blue,
+            alpha,
+        };
+        let mask = (render.XRenderCreateSolidFill)(self.display, &mask_color);
+        let mut attributes: xrender::XRenderPictureAttributes = std::mem::zeroed();
+        attributes.component_alpha = 1;
+        (render.XRenderChangePicture)(
+            self.display,
+            mask,
+            xrender::CPComponentAlpha as _,
+            &attributes,
+        );
+        (render.XRenderComposite)(
+            self.display,
+            xrender::PictOpOver,
+            texture.inner.picture,
+            mask,
+            scratch,
+            region.min.x as i32,
+            region.min.y as i32,
+            0,
+            0,
+            0,
+            0,
+            width,
+            height,
+        );
+        (render.XRenderFreePicture)(self.display, mask);
+
+        // The picture transform maps destination pixels back to scratch pixels.
+        let placement = options.transform(region).then(group_transform);
+        let bounds = region.transformed_bounds(&placement);
+        let (x, y) = (bounds.min.x.floor(), bounds.min.y.floor());
+        let to_screen = Transform2D::translation(region.min.x, region.min.y)
+            .then(&placement)
+            .then(&Transform2D::translation(-x, -y));
+        if let Some(t) = to_screen.inverse() {
+            let fixed = |v: f32| (v * 65536.0).round() as xrender::XFixed;
+            let mut transform = xrender::XTransform {
+                matrix: [
+                    [fixed(t.m11), fixed(t.m21), fixed(t.dx)],
+                    [fixed(t.m12), fixed(t.m22), fixed(t.dy)],
+                    [0, 0, fixed(1.0)],
+                ],
+            };
+            (render.XRenderSetPictureTransform)(self.display, scratch, &mut transform);
+            let tolerance = 1e-5 * (t.m11.abs() + t.m12.abs() + t.m21.abs() + t.m22.abs());
+            let axis_aligned = (t.m12.abs() <= tolerance && t.m21.abs() <= tolerance)
+                || (t.m11.abs() <= tolerance && t.m22.abs() <= tolerance);
+            if axis_aligned {
+                // Without rotation the drawn area matches the destination, padding the edges then stops the filter
+                // from blending the border with transparency, which would show seams between nine slice parts.
+                let mut attributes: xrender::XRenderPictureAttributes = std::mem::zeroed();
+                attributes.repeat = xrender::RepeatPad;
+                (render.XRenderChangePicture)(
+                    self.display,
+                    scratch,
+                    xrender::CPRepeat as _,
+                    &attributes,
+                );
+            }
+            let filter = match options.interpolation {
+                Interpolation::Nearest => c"nearest",
+                Interpolation::Linear => c"bilinear",
+            };
+            (render.XRenderSetPictureFilter)(
+                self.display,
+                scratch,
+                filter.as_ptr(),
+                std::ptr::null_mut(),
+                0,
+            );
+            (render.XRenderComposite)(
+                self.display,
+                xrender::PictOpOver,
+                scratch,
+                0,
+                picture,
+                0,
+                0,
+                0,
+                0,
+                x as i32,
+                y as i32,
+                (bounds.max.x.ceil() - x) as u32,
+                (bounds.max.y.ceil() - y) as u32,
+            );
+        }
+
+        (render.XRenderFreePicture)(self.display, scratch);
+        (self.instance.XFreePixmap)(self.display, pixmap);
+        Ok(())
     }
 
     pub fn remove_visual(&mut self, visual: &IDVisual) -> Result<(), Error> {
+        let removed = self.tree.remove(*visual);
+        if removed.iter().any(|(_, node)| node.data.is_some()) {
+            self.redraw()?;
+        }
         Ok(())
     }
+
+    pub fn create_group(&mut self) -> Result<IDVisual, Error> {
+        self.add_visual(None)
+    }
+
+    pub fn set_parent(&mut self, visual: &IDVisual, parent: &IDVisual) -> Result<(), Error> {
+        self.tree.set_parent(*visual, *parent)?;
+        self.redraw()
+    }
+
+    pub fn set_z_index(&mut self, visual: &IDVisual, z_index: i32) -> Result<(), Error> {
+        self.tree.set_z_index(*visual, z_index)?;
+        self.redraw()
+    }
+
+    pub fn set_visible(&mut self, visual: &IDVisual, visible: bool) -> Result<(), Error> {
+        self.node_mut(visual)?.visible = visible;
+        self.redraw()
+    }
+
+    pub fn is_visible(&self, visual: &IDVisual) -> bool {
+        self.tree.get(*visual).is_some_and(|n| n.visible)
+    }
+
+    pub fn appearance(&self, visual: &IDVisual) -> Option<Appearance> {
+        self.tree.get(*visual).map(Node::appearance)
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>, Error> {
+        unsafe {
+            // Xinerama knows the individual monitors, without it the root window is the only one.
+            if let Ok(xinerama) = xinerama::Xlib::open() {
+                if (xinerama.XineramaIsActive)(self.display) != 0 {
+                    let mut count = 0;
+                    let screens = (xinerama.XineramaQueryScreens)(self.display, &mut count);
+                    if !screens.is_null() {
+                        let monitors = std::slice::from_raw_parts(screens, count.max(0) as usize)
+                            .iter()
+                            .map(|s| Monitor {
+                                name: format!("screen {}", s.screen_number),
+                                area: Rect::from(s.x_org as f32, s.y_org as f32)
+                                    .sized(s.width as f32, s.height as f32),
+                                primary: s.screen_number == 0,
+                            })
+                            .collect::<Vec<_>>();
+                        (self.instance.XFree)(screens as *mut _);
+                        if !monitors.is_empty() {
+                            return Ok(monitors);
+                        }
+                    }
+                }
+            }
+            let root_window = (self.instance.XDefaultRootWindow)(self.display);
+            let mut attributes: xlib::XWindowAttributes =
+                std::mem::MaybeUninit::zeroed().assume_init();
+            let status =
+                (self.instance.XGetWindowAttributes)(self.display, root_window, &mut attributes);
+            if status != 1 {
+                return Err("failed to retrieve root window attributes".into());
+            }
+            Ok(vec![Monitor {
+                name: "screen 0".to_owned(),
+                area: Rect::from(0.0, 0.0).sized(attributes.width as f32, attributes.height as f32),
+                primary: true,
+            }])
+        }
+    }
+
+    pub fn set_opacity(&mut self, visual: &IDVisual, opacity: f32) -> Result<(), Error> {
+        self.node_mut(visual)?.opacity = opacity;
+        self.redraw()
+    }
+
+    pub fn set_tint(&mut self, visual: &IDVisual, tint: &Color) -> Result<(), Error> {
+        self.node_mut(visual)?.tint = *tint;
+        self.redraw()
+    }
+
+    pub fn set_transform(
+        &mut self,
+        visual: &IDVisual,
+        transform: &Transform2D,
+    ) -> Result<(), Error> {
+        self.node_mut(visual)?.transform = *transform;
+        self.redraw()
+    }
+
+    fn node_mut(&mut self, visual: &IDVisual) -> Result<&mut Node<Option<Content>>, Error> {
+        self.tree
+            .get_mut(*visual)
+            .ok_or_else(|| format!("visual {visual:?} does not exist").into())
+    }
 }
 
 pub fn run_msg_loop() -> Result<(), Error> {
@@ -319,5 +890,10 @@ pub fn run_msg_loop() -> Result<(), Error> {
 }
 
 pub fn setup() -> Result<(), Error> {
+    // Textures and visuals may be used from other threads than the one that created the overlay.
+    unsafe {
+        let instance = xlib::Xlib::open()?;
+        (instance.XInitThreads)();
+    }
     Ok(())
 }
@@ -0,0 +1,748 @@
+//! Scenes describe text, geometry and textures in a file, so an overlay can be set up without code.
+//!
+//! The format follows from the serde derives; JSON, TOML and RON are supported, picked by the file's extension. A
+//! TOML scene looks like:
+//! ```toml
+//! [fonts.title]
+//! size = 32
+//!
+//! [[items]]
+//! id = "greeting"
+//! position = { x = 10, y = 0 }
+//! [items.visual.Text]
+//! text = "Hello"
+//! layout = { min = { x = 100, y = 100 }, max = { x = 400, y = 150 } }
+//! color = "#ffffff"
+//! font = "title"
+//! ```
+
+use crate::{
+    Color, DrawGeometry, DrawTextureOptions, Error, Group, LineStyle, Overlay, Point, PreparedFont,
+    Rect, Stroke, TextProperties, VisualToken,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// The file formats a scene can be stored in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SceneFormat {
+    Json,
+    Toml,
+    Ron,
+}
+
+impl SceneFormat {
+    /// The format for a file's extension.
+    pub fn from_path(path: &Path) -> Result<SceneFormat, Error> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Ok(SceneFormat::Json),
+            Some("toml") => Ok(SceneFormat::Toml),
+            Some("ron") => Ok(SceneFormat::Ron),
+            _ => Err(format!(
+                "unknown scene format for {}, use .json, .toml or .ron",
+                path.display()
+            )
+            .into()),
+        }
+    }
+}
+
+/// What an item draws.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SceneVisual {
+    Text {
+        text: String,
+        layout: Rect,
+        color: Color,
+        /// Name of a font in [`Scene::fonts`], empty for the default font.
+        #[serde(default)]
+        font: String,
+    },
+    Geometry {
+        geometry: DrawGeometry,
+        stroke: Stroke,
+        #[serde(default)]
+        line_style: LineStyle,
+    },
+    /// An image file, relative paths are relative to the scene file.
+    Texture {
+        path: PathBuf,
+        #[serde(default)]
+        options: DrawTextureOptions,
+    },
+    /// A group holding more items, its properties apply to all of them.
+    Group { items: Vec<SceneItem> },
+}
+
+/// Something drawn by a scene, with the properties set on it after drawing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneItem {
+    /// Used to find the item's visual in the [`LoadedScene`], ids must be unique within the scene.
+    pub id: Option<String>,
+    pub visual: SceneVisual,
+    pub z_index: i32,
+    pub visible: bool,
+    pub opacity: f32,
+    pub tint: Color,
+    pub position: Point,
+    pub scale: f32,
+    pub rotation: f32,
+}
+
+impl Default for SceneItem {
+    fn default() -> Self {
+        Self {
+            id: None,
+            visual: SceneVisual::Group { items: vec![] },
+            z_index: 0,
+            visible: true,
+            opacity: 1.0,
+            tint: Color::WHITE,
+            position: Point::ORIGIN,
+            scale: 1.0,
+            rotation: 0.0,
+        }
+    }
+}
+
+impl SceneItem {
+    /// Copy the properties of a visual into the item.
+    fn capture(&mut self, token: &VisualToken) {
+        self.z_index = token.z_index();
+        self.visible = token.is_visible();
+        self.opacity = token.opacity();
+        self.tint = token.tint();
+        self.position = token.position();
+        self.scale = token.scale();
+        self.rotation = token.rotation();
+    }
+
+    /// Set the item's properties on a visual, only those that differ from `previous`.
+    fn apply(&self, token: &VisualToken, previous: &SceneItem) -> Result<(), Error> {
+        if self.z_index != previous.z_index {
+            token.set_z_index(self.z_index)?;
+        }
+        if self.visible != previous.visible {
+            token.set_visible(self.visible)?;
+        }
+        if self.opacity != previous.opacity {
+            token.set_opacity(self.opacity)?;
+        }
+        if self.tint != previous.tint {
+            token.set_tint(&self.tint)?;
+        }
+        if self.position != previous.position {
+            token.set_position(&self.position)?;
+        }
+        if self.scale != previous.scale {
+            token.set_scale(self.scale)?;
+        }
+        if self.rotation != previous.rotation {
+            token.set_rotation(self.rotation)?;
+        }
+        Ok(())
+    }
+}
+
+/// A description of visuals to draw, see the [module](self) documentation for an example.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    /// Fonts by name, for the text items.
+    pub fonts: BTreeMap<String, TextProperties>,
+    /// Drawn in order, later items are on top of earlier ones with the same z-index.
+    pub items: Vec<SceneItem>,
+}
+
+impl Scene {
+    pub fn from_str(text: &str, format: SceneFormat) -> Result<Scene, Error> {
+        Ok(match format {
+            SceneFormat::Json => serde_json::from_str(text)?,
+            SceneFormat::Toml => toml::from_str(text)?,
+            SceneFormat::Ron => ron::from_str(text)?,
+        })
+    }
+
+    pub fn to_string(&self, format: SceneFormat) -> Result<String, Error> {
+        Ok(match format {
+            SceneFormat::Json => serde_json::to_string_pretty(self)?,
+            SceneFormat::Toml => toml::to_string_pretty(self)?,
+            SceneFormat::Ron => ron::ser::to_string_pretty(self, Default::default())?,
+        })
+    }
+
+    /// Load a scene, texture paths are made relative to the current directory so the scene can be drawn from anywhere.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, Error> {
+        let path = path.as_ref();
+        let format = SceneFormat::from_path(path)?;
+        let mut scene = Scene::from_str(&std::fs::read_to_string(path)?, format)?;
+        if let Some(dir) = path.parent() {
+            scene.map_paths(&mut |p| dir.join(p));
+        }
+        Ok(scene)
+    }
+
+    /// Save the scene, texture paths below the file's directory are stored relative to it.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let format = SceneFormat::from_path(path)?;
+        let mut scene = self.clone();
+        if let Some(dir) = path.parent() {
+            scene.map_paths(&mut |p| p.strip_prefix(dir).map(Path::to_path_buf).unwrap_or(p));
+        }
+        std::fs::write(path, scene.to_string(format)?)?;
+        Ok(())
+    }
+
+    fn map_paths<F: FnMut(PathBuf) -> PathBuf>(&mut self, f: &mut F) {
+        fn map_items<F: FnMut(PathBuf) -> PathBuf>(items: &mut [SceneItem], f: &mut F) {
+            for item in items {
+                match &mut item.visual {
+                    SceneVisual::Texture { path, .. } => *path = f(std::mem::take(path)),
+                    SceneVisual::Group { items } => map_items(items, f),
+                    _ => {}
+                }
+            }
+        }
+        map_items(&mut self.items, f);
+    }
+
+    /// Check that ids are unique and fonts exist, before anything is drawn.
+    fn validate(&self) -> Result<(), Error> {
+        fn check(
+            items: &[SceneItem],
+            fonts: &BTreeMap<String, TextProperties>,
+            ids: &mut Vec<String>,
+        ) -> Result<(), Error> {
+            for item in items {
+                if let Some(id) = &item.id {
+                    if ids.contains(id) {
+                        return Err(format!("scene id {id:?} is used more than once").into());
+                    }
+                    ids.push(id.clone());
+                }
+                match &item.visual {
+                    SceneVisual::Text { font, .. }
+                        if !font.is_empty() && !fonts.contains_key(font) =>
+                    {
+                        return Err(format!("scene font {font:?} isn't defined").into());
+                    }
+                    SceneVisual::Group { items } => check(items, fonts, ids)?,
+                    _ => {}
+                }
+            }
+            Ok(())
+        }
+        check(&self.items, &self.fonts, &mut vec![])
+    }
+}
+
+/// A drawn item, a group holds the entries of its items.
+struct Entry {
+    /// The id, or the position among its siblings for items without one.
+    key: String,
+    /// The item as drawn, without the items of a group.
+    item: SceneItem,
+    token: VisualToken,
+    group: Option<Group>,
+    children: Vec<Entry>,
+}
+
+/// Changes to visuals that are kept, made once everything new is drawn so a failed update leaves them as they were.
+enum Deferred {
+    Properties {
+        token: VisualToken,
+        from: Box<SceneItem>,
+        to: Box<SceneItem>,
+    },
+    Order {
+        parent: Group,
+        children: Vec<(VisualToken, Option<Group>)>,
+    },
+}
+
+/// The visuals drawn for a scene, they are removed when this is dropped.
+pub struct LoadedScene {
+    overlay: Overlay,
+    fonts: BTreeMap<String, TextProperties>,
+    prepared: HashMap<String, PreparedFont>,
+    default_font: Option<PreparedFont>,
+    root: Group,
+    entries: Vec<Entry>,
+    by_id: HashMap<String, VisualToken>,
+}
+
+impl std::fmt::Debug for LoadedScene {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "LoadedScene {} items", self.entries.len())
+    }
+}
+
+impl LoadedScene {
+    /// The visual of the item with this id, groups included.
+    pub fn get(&self, id: &str) -> Option<&VisualToken> {
+        self.by_id.get(id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.by_id.keys().map(String::as_str)
+    }
+
+    /// The group holding the whole scene.
+    pub fn group(&self) -> &Group {
+        &self.root
+    }
+
+    /// The scene as it was loaded, with the current properties of its visuals; changes made through the tokens, like
+    /// moving or hiding them, end up in the snapshot.
+    pub fn snapshot(&self) -> Scene {
+        fn capture(entries: &[Entry]) -> Vec<SceneItem> {
+            entries
+                .iter()
+                .map(|entry| {
+                    let mut item = entry.item.clone();
+                    item.capture(&entry.token);
+                    if entry.group.is_some() {
+                        item.visual = SceneVisual::Group {
+                            items: capture(&entry.children),
+                        };
+                    }
+                    item
+                })
+                .collect()
+        }
+        Scene {
+            fonts: self.fonts.clone(),
+            items: capture(&self.entries),
+        }
+    }
+
+    /// Change the drawn scene into `scene`, only items whose visual changed are drawn again; items are matched by id,
+    /// or by position for those without one. Properties of kept items are set where they differ from the previous
+    /// scene, changes made through the tokens are kept otherwise.
+    ///
+    /// If the update fails the previous scene stays, only the stacking of its items may have changed.
+    pub fn update(&mut self, scene: &Scene) -> Result<(), Error> {
+        scene.validate()?;
+        let overlay = self.overlay.clone();
+        overlay.transaction(|tx| {
+            let mut changed_fonts = vec![];
+            let mut prepared = HashMap::new();
+            for (name, properties) in &scene.fonts {
+                match (self.fonts.get(name), self.prepared.get(name)) {
+                    (Some(old), Some(font)) if old == properties => {
+                        prepared.insert(name.clone(), font.clone());
+                    }
+                    _ => {
+                        changed_fonts.push(name.clone());
+                        prepared.insert(name.clone(), tx.prepare_font(properties)?);
+                    }
+                }
+            }
+            let previous = std::mem::replace(&mut self.prepared, prepared);
+            let mut deferred = vec![];
+            let old = std::mem::take(&mut self.entries);
+            let root = self.root.clone();
+            let result = self.reconcile(&old, &scene.items, &root, &changed_fonts, &mut deferred);
+            let entries = match result {
+                Ok(entries) => entries,
+                Err(e) => {
+                    self.entries = old;
+                    self.prepared = previous;
+                    return Err(e);
+                }
+            };
+            if let Err(e) = apply_deferred(&deferred) {
+                self.entries = old;
+                self.prepared = previous;
+                return Err(e);
+            }
+            self.entries = entries;
+            self.fonts = scene.fonts.clone();
+            self.by_id = ids(&self.entries);
+            Ok(())
+        })
+    }
+
+    fn font(&mut self, name: &str) -> Result<PreparedFont, Error> {
+        if name.is_empty() {
+            if self.default_font.is_none() {
+                self.default_font = Some(self.overlay.prepare_font(&TextProperties::default())?);
+            }
+            return Ok(self.default_font.clone().unwrap());
+        }
+        self.prepared
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("scene font {name:?} isn't defined").into())
+    }
+
+    fn draw(&mut self, visual: &SceneVisual) -> Result<(VisualToken, Option<Group>), Error> {
+        let overlay = self.overlay.clone();
+        Ok(match visual {
+            SceneVisual::Text {
+                text,
+                layout,
+                color,
+                font,
+            } => {
+                let font = self.font(font)?;
+                (overlay.draw_text(text, layout, color, &font)?, None)
+            }
+            SceneVisual::Geometry {
+                geometry,
+                stroke,
+                line_style,
+            } => (overlay.draw_geometry(geometry, stroke, line_style)?, None),
+            SceneVisual::Texture { path, options } => {
+                let texture = overlay.load_texture_cached(path)?;
+                (overlay.draw_texture_with_options(&texture, options)?, None)
+            }
+            SceneVisual::Group { .. } => {
+                let group = overlay.create_group()?;
+                ((*group).clone(), Some(group))
+            }
+        })
+    }
+
+    /// The entries for `items` in `parent`, reusing those of `old` whose visual is unchanged.
+    fn reconcile(
+        &mut self,
+        old: &[Entry],
+        items: &[SceneItem],
+        parent: &Group,
+        changed_fonts: &[String],
+        deferred: &mut Vec<Deferred>,
+    ) -> Result<Vec<Entry>, Error> {
+        let split: Vec<(String, SceneItem, Vec<SceneItem>)> = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let mut shallow = item.clone();
+                let children = match &mut shallow.visual {
+                    SceneVisual::Group { items } => std::mem::take(items),
+                    _ => vec![],
+                };
+                let key = item.id.clone().unwrap_or_else(|| format!("#{index}"));
+                (key, shallow, children)
+            })
+            .collect();
+        let kept = match_items(
+            &old.iter()
+                .map(|e| (e.key.as_str(), &e.item.visual))
+                .collect::<Vec<_>>(),
+            &split
+                .iter()
+                .map(|(key, item, _)| (key.as_str(), &item.visual))
+                .collect::<Vec<_>>(),
+            changed_fonts,
+        );
+        let mut entries = vec![];
+        for ((key, shallow, children), keep) in split.into_iter().zip(kept.iter().copied()) {
+            let entry = match keep {
+                Some(i) => {
+                    let old = &old[i];
+                    deferred.push(Deferred::Properties {
+                        token: old.token.clone(),
+                        from: Box::new(old.item.clone()),
+                        to: Box::new(shallow.clone()),
+                    });
+                    let children = match &old.group {
+                        Some(group) => self.reconcile(
+                            &old.children,
+                            &children,
+                            group,
+                            changed_fonts,
+                            deferred,
+                        )?,
+                        None => vec![],
+                    };
+                    Entry {
+                        key,
+                        item: shallow,
+                        token: old.token.clone(),
+                        group: old.group.clone(),
+                        children,
+                    }
+                }
+                None => {
+                    let (token, group) = self.draw(&shallow.visual)?;
+                    match &group {
+                        Some(group) => parent.add_group(group)?,
+                        None => parent.add(&token)?,
+                    }
+                    shallow.apply(&token, &SceneItem::default())?;
+                    let children = match &group {
+                        Some(group) => {
+                            self.reconcile(&[], &children, group, changed_fonts, deferred)?
+                        }
+                        None => vec![],
+                    };
+                    Entry {
+                        key,
+                        item: shallow,
+                        token,
+                        group,
+                        children,
+                    }
+                }
+            };
+            entries.push(entry);
+        }
+        // Kept entries stay where they were and new ones are drawn on top, restack if that isn't the scene's order.
+        if !kept.iter().map(|k| k.unwrap_or(usize::MAX)).is_sorted() {
+            deferred.push(Deferred::Order {
+                parent: parent.clone(),
+                children: entries
+                    .iter()
+                    .map(|e| (e.token.clone(), e.group.clone()))
+                    .collect(),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Make the deferred changes, if one fails the property changes made so far are undone.
+fn apply_deferred(deferred: &[Deferred]) -> Result<(), Error> {
+    let mut applied = vec![];
+    for change in deferred {
+        let result = match change {
+            Deferred::Properties { token, from, to } => {
+                applied.push((token, from, to));
+                to.apply(token, from)
+            }
+            Deferred::Order { parent, children } => {
+                children.iter().try_for_each(|(token, group)| match group {
+                    Some(group) => parent.add_group(group),
+                    None => parent.add(token),
+                })
+            }
+        };
+        if let Err(e) = result {
+            for (token, from, to) in applied.into_iter().rev() {
+                let _ = from.apply(token, to);
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// For every new item, the old item that can be kept because its key and visual are unchanged.
+fn match_items(
+    old: &[(&str, &SceneVisual)],
+    new: &[(&str, &SceneVisual)],
+    changed_fonts: &[String],
+) -> Vec<Option<usize>> {
+    let mut used = vec![false; old.len()];
+    new.iter()
+        .map(|(key, visual)| {
+            if let SceneVisual::Text { font, .. } = visual {
+                if changed_fonts.contains(font) {
+                    return None;
+                }
+            }
+            let i = old
+                .iter()
+                .enumerate()
+                .position(|(i, (k, v))| !used[i] && k == key && v == visual)?;
+            used[i] = true;
+            Some(i)
+        })
+        .collect()
+}
+
+/// The visuals of all entries with an id.
+fn ids(entries: &[Entry]) -> HashMap<String, VisualToken> {
+    let mut by_id = HashMap::new();
+    fn collect(entries: &[Entry], by_id: &mut HashMap<String, VisualToken>) {
+        for entry in entries {
+            if let Some(id) = &entry.item.id {
+                by_id.insert(id.clone(), entry.token.clone());
+            }
+            collect(&entry.children, by_id);
+        }
+    }
+    collect(entries, &mut by_id);
+    by_id
+}
+
+impl Overlay {
+    /// Load a scene file and draw it, see [`Scene::load`].
+    pub fn load_scene<P: AsRef<Path>>(&self, path: P) -> Result<LoadedScene, Error> {
+        self.draw_scene(&Scene::load(path)?)
+    }
+
+    /// Draw all items of a scene at once, into a new group.
+    pub fn draw_scene(&self, scene: &Scene) -> Result<LoadedScene, Error> {
+        let mut loaded = LoadedScene {
+            overlay: self.clone(),
+            fonts: BTreeMap::new(),
+            prepared: HashMap::new(),
+            default_font: None,
+            root: self.create_group()?,
+            entries: vec![],
+            by_id: HashMap::new(),
+        };
+        loaded.update(scene)?;
+        Ok(loaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> Scene {
+        Scene {
+            fonts: [(
+                "title".to_owned(),
+                TextProperties {
+                    size: 32.0,
+                    ..Default::default()
+                },
+            )]
+            .into_iter()
+            .collect(),
+            items: vec![
+                SceneItem {
+                    id: Some("greeting".to_owned()),
+                    visual: SceneVisual::Text {
+                        text: "Hello".to_owned(),
+                        layout: Rect::from(100.0, 100.0).sized(300.0, 50.0),
+                        color: Color::WHITE,
+                        font: "title".to_owned(),
+                    },
+                    position: Point::new(10.0, 0.0),
+                    ..Default::default()
+                },
+                SceneItem {
+                    id: Some("hud".to_owned()),
+                    visual: SceneVisual::Group {
+                        items: vec![
+                            SceneItem {
+                                visual: SceneVisual::Geometry {
+                                    geometry: DrawGeometry::new()
+                                        .rectangle(&Rect::from(0.0, 0.0).sized(10.0, 10.0)),
+                                    stroke: Stroke {
+                                        color: Color::rgb(255, 0, 0),
+                                        width: 2.0,
+                                    },
+                                    line_style: Default::default(),
+                                },
+                                ..Default::default()
+                            },
+                            SceneItem {
+                                id: Some("logo".to_owned()),
+                                visual: SceneVisual::Texture {
+                                    path: PathBuf::from("images/logo.png"),
+                                    options: DrawTextureOptions::new(
+                                        Rect::from(0.0, 0.0).sized(64.0, 64.0),
+                                    ),
+                                },
+                                opacity: 0.5,
+                                ..Default::default()
+                            },
+                        ],
+                    },
+                    visible: false,
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_scene_formats() {
+        let scene = example();
+        for format in [SceneFormat::Json, SceneFormat::Toml, SceneFormat::Ron] {
+            let text = scene.to_string(format).unwrap();
+            assert_eq!(Scene::from_str(&text, format).unwrap(), scene, "{format:?}");
+        }
+        // The example in the module documentation.
+        let toml = r##"
+            [fonts.title]
+            size = 32
+
+            [[items]]
+            id = "greeting"
+            position = { x = 10, y = 0 }
+            [items.visual.Text]
+            text = "Hello"
+            layout = { min = { x = 100, y = 100 }, max = { x = 400, y = 150 } }
+            color = "#ffffff"
+            font = "title"
+        "##;
+        let parsed = Scene::from_str(toml, SceneFormat::Toml).unwrap();
+        assert_eq!(parsed.items[0], scene.items[0]);
+        assert_eq!(parsed.fonts, scene.fonts);
+
+        let example =
+            Scene::from_str(include_str!("../examples/scene.toml"), SceneFormat::Toml).unwrap();
+        assert!(example.validate().is_ok());
+        assert_eq!(example.items[1].opacity, 0.8);
+
+        assert!(scene.validate().is_ok());
+        let mut duplicate = scene.clone();
+        duplicate.items[1].id = Some("greeting".to_owned());
+        assert!(duplicate.validate().is_err());
+        let mut missing_font = scene.clone();
+        missing_font.fonts.clear();
+        assert!(missing_font.validate().is_err());
+    }
+
+    #[test]
+    fn test_scene_match() {
+        let text = |t: &str, font: &str| SceneVisual::Text {
+            text: t.to_owned(),
+            layout: Rect::from(0.0, 0.0).sized(10.0, 10.0),
+            color: Color::WHITE,
+            font: font.to_owned(),
+        };
+        let (a, b, c) = (text("a", ""), text("b", "title"), text("c", ""));
+        let old = [("#0", &a), ("title", &b), ("#2", &c)];
+        // Unchanged items are kept, also when moved; a changed visual or font is drawn again.
+        assert_eq!(
+            match_items(&old, &old, &[]),
+            vec![Some(0), Some(1), Some(2)]
+        );
+        assert_eq!(
+            match_items(&old, &old, &["title".to_owned()]),
+            vec![Some(0), None, Some(2)]
+        );
+        let changed = text("changed", "");
+        assert_eq!(
+            match_items(&old, &[("title", &b), ("#1", &changed), ("#2", &c)], &[]),
+            vec![Some(1), None, Some(2)]
+        );
+        // Items without an id are matched by position.
+        assert_eq!(match_items(&old, &[("#1", &a)], &[]), vec![None]);
+    }
+
+    #[test]
+    fn test_scene_paths() {
+        let dir = std::env::temp_dir().join(format!("scene-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("scene.ron");
+        example().save(&file).unwrap();
+        let loaded = Scene::load(&file).unwrap();
+        let SceneVisual::Group { items } = &loaded.items[1].visual else {
+            panic!("expected a group");
+        };
+        let SceneVisual::Texture { path, .. } = &items[1].visual else {
+            panic!("expected a texture");
+        };
+        assert_eq!(*path, dir.join("images/logo.png"));
+        // Saving again stores the path relative to the scene.
+        loaded.save(&file).unwrap();
+        assert_eq!(
+            Scene::from_str(&std::fs::read_to_string(&file).unwrap(), SceneFormat::Ron).unwrap(),
+            example()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
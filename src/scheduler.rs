@@ -0,0 +1,144 @@
+//! Timed tasks that run on a background thread owned by the overlay, used to animate visuals.
+//!
+//! The thread is started when the first task is scheduled and stops once the last [`Overlay`] handle is dropped,
+//! tasks are passed the overlay when they run and should not hold on to a clone of it themselves as that would
+//! keep the overlay alive.
+
+use crate::{Overlay, WeakOverlay};
+
+use parking_lot::{Condvar, Mutex};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A task is called at or after its due time with the current time, it returns when it wants to run next or
+/// `None` when it is done.
+pub(crate) type Task = Box<dyn FnMut(&Overlay, Instant) -> Option<Instant> + Send>;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub(crate) struct TaskId(u64);
+
+struct Entry {
+    id: TaskId,
+    due: Instant,
+    task: Task,
+}
+
+#[derive(Default)]
+struct State {
+    tasks: Vec<Entry>,
+    next_id: u64,
+    started: bool,
+    stop: bool,
+    /// The task that is currently running, it is not in `tasks` while it runs.
+    running: Option<TaskId>,
+    /// Set if the running task was cancelled while it ran, it is then not rescheduled.
+    cancel_running: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+pub(crate) struct Scheduler {
+    shared: Arc<Shared>,
+    overlay: Arc<WeakOverlay>,
+}
+
+impl Scheduler {
+    pub fn new(overlay: WeakOverlay) -> Scheduler {
+        Scheduler {
+            shared: Default::default(),
+            overlay: Arc::new(overlay),
+        }
+    }
+
+    /// Run `task` at `due`, tasks that are due at the same time run in no particular order.
+    pub fn schedule(&self, due: Instant, task: Task) -> TaskId {
+        let mut state = self.shared.state.lock();
+        let id = TaskId(state.next_id);
+        state.next_id += 1;
+        state.tasks.push(Entry { id, due, task });
+        if !state.started {
+            state.started = true;
+            let shared = self.shared.clone();
+            let overlay = self.overlay.clone();
+            std::thread::Builder::new()
+                .name("overlay-scheduler".to_owned())
+                .spawn(move || run(shared, overlay))
+                .expect("failed to spawn scheduler thread");
+        }
+        self.shared.condvar.notify_one();
+        id
+    }
+
+    /// Remove a task, if it is running right now it finishes but isn't scheduled again.
+    pub fn cancel(&self, id: TaskId) {
+        let removed = {
+            let mut state = self.shared.state.lock();
+            if state.running == Some(id) {
+                state.cancel_running = true;
+            }
+            let index = state.tasks.iter().position(|e| e.id == id);
+            index.map(|i| state.tasks.swap_remove(i))
+        };
+        // Tasks may own visuals, those lock the overlay when dropped so this happens without holding the state.
+        drop(removed);
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shared.state.lock().stop = true;
+        self.shared.condvar.notify_one();
+    }
+}
+
+fn run(shared: Arc<Shared>, overlay: Arc<WeakOverlay>) {
+    let mut state = shared.state.lock();
+    loop {
+        if state.stop {
+            break;
+        }
+        let now = Instant::now();
+        let Some(index) = state.tasks.iter().position(|e| e.due <= now) else {
+            match state.tasks.iter().map(|e| e.due).min() {
+                Some(due) => {
+                    shared.condvar.wait_until(&mut state, due);
+                }
+                None => shared.condvar.wait(&mut state),
+            }
+            continue;
+        };
+
+        let mut entry = state.tasks.swap_remove(index);
+        state.running = Some(entry.id);
+        state.cancel_running = false;
+        drop(state);
+
+        let Some(handle) = overlay.upgrade() else {
+            return;
+        };
+        let next = (entry.task)(&handle, now);
+        drop(handle);
+
+        state = shared.state.lock();
+        state.running = None;
+        match next {
+            Some(due) if !state.cancel_running => {
+                entry.due = due;
+                state.tasks.push(entry);
+            }
+            _ => {
+                drop(state);
+                drop(entry);
+                state = shared.state.lock();
+            }
+        }
+    }
+    // Drop the remaining tasks without holding the lock, see `cancel`.
+    let tasks = std::mem::take(&mut state.tasks);
+    drop(state);
+    drop(tasks);
+}
@@ -0,0 +1,655 @@
+//! A server that lets other processes draw on the overlay, enabled by the `server` feature.
+//!
+//! Clients connect to a Unix domain socket, or a named pipe on Windows, and write one JSON [`Request`] per line.
+//! Every request is answered with one JSON [`Response`] line, lines are limited to 1 MiB. Visuals are named by the
+//! client, drawing with an id that is in use replaces that visual. For example:
+//! ```text
+//! {"DrawText": {"id": "status", "text": "Ready", "layout": {"min": {"x": 0, "y": 0}, "max": {"x": 200, "y": 40}}, "color": "#ffffff"}}
+//! {"Update": {"id": "status", "position": {"x": 10, "y": 100}, "opacity": 0.5}}
+//! {"Remove": {"id": "status"}}
+//! "Clear"
+//! ```
+//! Everything a client drew stays on the overlay after it disconnects, until it is removed or cleared.
+
+use crate::{
+    Color, DrawGeometry, DrawTextureOptions, Error, LineStyle, Overlay, Point, PreparedFont, Rect,
+    Stroke, TextProperties, VisualToken,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Properties to set on a visual, those that are `None` are left as they are.
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Properties {
+    pub z_index: Option<i32>,
+    pub visible: Option<bool>,
+    pub opacity: Option<f32>,
+    pub tint: Option<Color>,
+    pub position: Option<Point>,
+    pub scale: Option<f32>,
+    pub rotation: Option<f32>,
+}
+
+impl Properties {
+    fn apply(&self, token: &VisualToken) -> Result<(), Error> {
+        if let Some(z_index) = self.z_index {
+            token.set_z_index(z_index)?;
+        }
+        if let Some(visible) = self.visible {
+            token.set_visible(visible)?;
+        }
+        if let Some(opacity) = self.opacity {
+            token.set_opacity(opacity)?;
+        }
+        if let Some(tint) = &self.tint {
+            token.set_tint(tint)?;
+        }
+        if let Some(position) = &self.position {
+            token.set_position(position)?;
+        }
+        if let Some(scale) = self.scale {
+            token.set_scale(scale)?;
+        }
+        if let Some(rotation) = self.rotation {
+            token.set_rotation(rotation)?;
+        }
+        Ok(())
+    }
+}
+
+/// A command sent by a client, the properties of the draw requests are set on the new visual.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Request {
+    DrawText {
+        id: String,
+        text: String,
+        layout: Rect,
+        color: Color,
+        #[serde(default)]
+        font: TextProperties,
+        #[serde(flatten)]
+        properties: Properties,
+    },
+    DrawGeometry {
+        id: String,
+        geometry: DrawGeometry,
+        stroke: Stroke,
+        #[serde(default)]
+        line_style: LineStyle,
+        #[serde(flatten)]
+        properties: Properties,
+    },
+    /// An image file, relative paths are relative to the working directory of the server.
+    DrawTexture {
+        id: String,
+        path: PathBuf,
+        options: DrawTextureOptions,
+        #[serde(flatten)]
+        properties: Properties,
+    },
+    Update {
+        id: String,
+        #[serde(flatten)]
+        properties: Properties,
+    },
+    Remove {
+        id: String,
+    },
+    /// Remove everything drawn through the server.
+    Clear,
+}
+
+/// The answer to a request, a draw request that fails doesn't change the overlay.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Response {
+    Ok,
+    Error(String),
+}
+
+/// The address used when none is given, in `XDG_RUNTIME_DIR` or the temporary directory on Unix.
+pub fn default_address() -> PathBuf {
+    #[cfg(unix)]
+    {
+        std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir)
+            .join("screen-overlay.sock")
+    }
+    #[cfg(windows)]
+    {
+        PathBuf::from(r"\\.\pipe\screen-overlay")
+    }
+}
+
+/// Longest request line a client may send, the connection is closed after a longer one.
+const MAX_LINE_LENGTH: u64 = 1 << 20;
+
+/// Number of prepared fonts kept, the least recently used one is dropped to make room for another.
+const MAX_FONTS: usize = 16;
+
+#[derive(Default)]
+struct State {
+    visuals: HashMap<String, VisualToken>,
+    /// Fonts are prepared once for every distinct set of properties, the most recently used last.
+    fonts: Vec<(TextProperties, PreparedFont)>,
+}
+
+impl State {
+    fn font(&mut self, overlay: &Overlay, font: &TextProperties) -> Result<PreparedFont, Error> {
+        let entry = match self.fonts.iter().position(|(p, _)| p == font) {
+            Some(index) => self.fonts.remove(index),
+            None => (font.clone(), overlay.prepare_font(font)?),
+        };
+        if self.fonts.len() >= MAX_FONTS {
+            self.fonts.remove(0);
+        }
+        let prepared = entry.1.clone();
+        self.fonts.push(entry);
+        Ok(prepared)
+    }
+}
+
+/// Draws on an overlay on behalf of clients, cloning it gives a handle to the same visuals.
+#[derive(Clone)]
+pub struct Server {
+    overlay: Overlay,
+    /// Only locked while holding the overlay's lock.
+    state: Arc<Mutex<State>>,
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Server")
+    }
+}
+
+impl Server {
+    pub fn new(overlay: &Overlay) -> Server {
+        Server {
+            overlay: overlay.clone(),
+            state: Default::default(),
+        }
+    }
+
+    /// Carry out a single request, the changes it makes become visible together.
+    pub fn handle(&self, request: &Request) -> Result<(), Error> {
+        self.overlay.transaction(|overlay| {
+            let mut state = self.state.lock();
+            let (id, token, properties) = match request {
+                Request::DrawText {
+                    id,
+                    text,
+                    layout,
+                    color,
+                    font,
+                    properties,
+                } => {
+                    let prepared = state.font(overlay, font)?;
+                    let token = overlay.draw_text(text, layout, color, &prepared)?;
+                    (id, token, properties)
+                }
+                Request::DrawGeometry {
+                    id,
+                    geometry,
+                    stroke,
+                    line_style,
+                    properties,
+                } => {
+                    let token = overlay.draw_geometry(geometry, stroke, line_style)?;
+                    (id, token, properties)
+                }
+                Request::DrawTexture {
+                    id,
+                    path,
+                    options,
+                    properties,
+                } => {
+                    let texture = overlay.load_texture_cached(path)?;
+                    let token = overlay.draw_texture_with_options(&texture, options)?;
+                    (id, token, properties)
+                }
+                Request::Update { id, properties } => {
+                    let token = state
+                        .visuals
+                        .get(id)
+                        .ok_or_else(|| format!("no visual with id {id:?}"))?;
+                    return properties.apply(token);
+                }
+                Request::Remove { id } => {
+                    state
+                        .visuals
+                        .remove(id)
+                        .ok_or_else(|| format!("no visual with id {id:?}"))?;
+                    return Ok(());
+                }
+                Request::Clear => {
+                    state.visuals.clear();
+                    return Ok(());
+                }
+            };
+            // Dropping the token of a failed draw removes it again.
+            properties.apply(&token)?;
+            state.visuals.insert(id.clone(), token);
+            Ok(())
+        })
+    }
+
+    /// Start listening on the address, see [`default_address`]. Fails if another server is using it.
+    pub fn bind<P: AsRef<Path>>(&self, address: P) -> Result<Listener, Error> {
+        Ok(Listener {
+            server: self.clone(),
+            endpoint: transport::Endpoint::bind(address.as_ref())?,
+        })
+    }
+}
+
+/// A bound address, connections are accepted by [`Listener::run`].
+pub struct Listener {
+    server: Server,
+    endpoint: transport::Endpoint,
+}
+
+impl std::fmt::Debug for Listener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Listener")
+    }
+}
+
+impl Listener {
+    /// Accept clients until accepting fails, each client is served on its own thread.
+    pub fn run(mut self) -> Result<(), Error> {
+        loop {
+            let connection = self.endpoint.accept()?;
+            let server = self.server.clone();
+            std::thread::Builder::new()
+                .name("overlay-server-connection".to_owned())
+                .spawn(move || {
+                    if let Err(e) = serve(&connection, |request| server.handle(request)) {
+                        eprintln!("client connection failed: {e}");
+                    }
+                })?;
+        }
+    }
+}
+
+/// The response to a line sent by a client.
+fn respond<F: FnMut(&Request) -> Result<(), Error>>(line: &str, handle: &mut F) -> Response {
+    match serde_json::from_str::<Request>(line) {
+        Ok(request) => match handle(&request) {
+            Ok(()) => Response::Ok,
+            Err(e) => Response::Error(e.to_string()),
+        },
+        Err(e) => Response::Error(format!("invalid request: {e}")),
+    }
+}
+
+/// Answer the requests on a connection until the client closes it, empty lines are ignored.
+///
+/// A line longer than [`MAX_LINE_LENGTH`] is answered with an error and closes the connection.
+fn serve<C, F>(connection: &C, mut handle: F) -> Result<(), Error>
+where
+    for<'a> &'a C: Read + Write,
+    F: FnMut(&Request) -> Result<(), Error>,
+{
+    let mut writer = connection;
+    let mut reader = BufReader::new(connection);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        // One byte more than allowed, that tells a line that is too long apart from one that just fits.
+        let read = reader
+            .by_ref()
+            .take(MAX_LINE_LENGTH + 1)
+            .read_line(&mut line)?;
+        if read == 0 {
+            return Ok(());
+        }
+        let too_long = read as u64 > MAX_LINE_LENGTH && !line.ends_with('\n');
+        let response = if too_long {
+            Response::Error(format!("requests are limited to {MAX_LINE_LENGTH} bytes"))
+        } else if line.trim().is_empty() {
+            continue;
+        } else {
+            respond(&line, &mut handle)
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        if too_long {
+            return Err("client sent a request that is too long".into());
+        }
+    }
+}
+
+#[cfg(unix)]
+mod transport {
+    use crate::Error;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+
+    pub struct Endpoint {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl Endpoint {
+        pub fn bind(path: &Path) -> Result<Endpoint, Error> {
+            if UnixStream::connect(path).is_ok() {
+                return Err(
+                    format!("{} is already served by another process", path.display()).into(),
+                );
+            }
+            // A socket nobody listens on is left behind by a server that didn't exit cleanly.
+            let stale = std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket());
+            if stale {
+                std::fs::remove_file(path)?;
+            } else if std::fs::symlink_metadata(path).is_ok() {
+                return Err(format!("{} exists and is not a socket", path.display()).into());
+            }
+            // Only the user running the server may draw. The socket is bound in a directory only this user can
+            // enter and moved into place once its permissions are restricted, so nobody can connect before that.
+            let file_name = path.file_name().ok_or("the socket path has no file name")?;
+            let private = path.with_file_name(format!(
+                ".{}.{}",
+                file_name.to_string_lossy(),
+                std::process::id()
+            ));
+            std::fs::DirBuilder::new().mode(0o700).create(&private)?;
+            let bound = private.join("socket");
+            let result = UnixListener::bind(&bound).and_then(|listener| {
+                std::fs::set_permissions(&bound, std::fs::Permissions::from_mode(0o600))?;
+                std::fs::rename(&bound, path)?;
+                Ok(listener)
+            });
+            let _ = std::fs::remove_file(&bound);
+            let _ = std::fs::remove_dir(&private);
+            Ok(Endpoint {
+                listener: result?,
+                path: path.to_path_buf(),
+            })
+        }
+
+        pub fn accept(&mut self) -> Result<UnixStream, Error> {
+            Ok(self.listener.accept()?.0)
+        }
+    }
+
+    impl Drop for Endpoint {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod transport {
+    use crate::Error;
+    use std::io::{Read, Write};
+    use std::path::{Path, PathBuf};
+    use windows::core::HSTRING;
+    use windows::Win32::Foundation::{
+        CloseHandle, ERROR_BROKEN_PIPE, ERROR_PIPE_CONNECTED, HANDLE,
+    };
+    use windows::Win32::Storage::FileSystem::{
+        FlushFileBuffers, ReadFile, WriteFile, FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows::Win32::System::Pipes::{
+        ConnectNamedPipe, CreateNamedPipeW, DisconnectNamedPipe, PIPE_READMODE_BYTE,
+        PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    const BUFFER_SIZE: u32 = 64 * 1024;
+
+    /// An instance of the named pipe, each client connects to its own instance.
+    pub struct Pipe(HANDLE);
+    // The handle is only used for blocking reads and writes, which are allowed from any thread.
+    unsafe impl Send for Pipe {}
+    unsafe impl Sync for Pipe {}
+
+    impl Pipe {
+        fn create(name: &Path, first: bool) -> Result<Pipe, Error> {
+            let mut open_mode = PIPE_ACCESS_DUPLEX;
+            if first {
+                // Fails if another process already created the pipe.
+                open_mode |= FILE_FLAG_FIRST_PIPE_INSTANCE;
+            }
+            let handle = unsafe {
+                CreateNamedPipeW(
+                    &HSTRING::from(name),
+                    open_mode,
+                    PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                    PIPE_UNLIMITED_INSTANCES,
+                    BUFFER_SIZE,
+                    BUFFER_SIZE,
+                    0,
+                    None,
+                )
+            };
+            if handle.is_invalid() {
+                return Err(format!(
+                    "failed to create pipe {}: {}",
+                    name.display(),
+                    std::io::Error::last_os_error()
+                )
+                .into());
+            }
+            Ok(Pipe(handle))
+        }
+    }
+
+    impl Read for &Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let mut read = 0;
+            match unsafe { ReadFile(self.0, Some(buf), Some(&mut read), None) } {
+                Ok(()) => Ok(read as usize),
+                // The client closed its end.
+                Err(e) if e.code() == ERROR_BROKEN_PIPE.to_hresult() => Ok(0),
+                Err(e) => Err(e.into()),
+            }
+        }
+    }
+
+    impl Write for &Pipe {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let mut written = 0;
+            unsafe { WriteFile(self.0, Some(buf), Some(&mut written), None)? };
+            Ok(written as usize)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            unsafe { FlushFileBuffers(self.0)? };
+            Ok(())
+        }
+    }
+
+    impl Drop for Pipe {
+        fn drop(&mut self) {
+            unsafe {
+                let _ = DisconnectNamedPipe(self.0);
+                let _ = CloseHandle(self.0);
+            }
+        }
+    }
+
+    pub struct Endpoint {
+        name: PathBuf,
+        /// The instance the next client connects to.
+        next: Pipe,
+    }
+
+    impl Endpoint {
+        pub fn bind(name: &Path) -> Result<Endpoint, Error> {
+            Ok(Endpoint {
+                name: name.to_path_buf(),
+                next: Pipe::create(name, true)?,
+            })
+        }
+
+        pub fn accept(&mut self) -> Result<Pipe, Error> {
+            match unsafe { ConnectNamedPipe(self.next.0, None) } {
+                // The client connected between creating the instance and waiting for it.
+                Err(e) if e.code() != ERROR_PIPE_CONNECTED.to_hresult() => return Err(e.into()),
+                _ => {}
+            }
+            let next = Pipe::create(&self.name, false)?;
+            Ok(std::mem::replace(&mut self.next, next))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_parse() {
+        let text: Request = serde_json::from_str(
+            r##"{"DrawText": {"id": "status", "text": "Ready", "color": "#ffffff",
+                "layout": {"min": {"x": 0, "y": 0}, "max": {"x": 200, "y": 40}},
+                "font": {"size": 32}, "opacity": 0.5}}"##,
+        )
+        .unwrap();
+        let Request::DrawText {
+            id,
+            font,
+            properties,
+            ..
+        } = text
+        else {
+            panic!("expected text, got {text:?}");
+        };
+        assert_eq!(id, "status");
+        assert_eq!(font.size, 32.0);
+        assert_eq!(font.font, TextProperties::default().font);
+        assert_eq!(properties.opacity, Some(0.5));
+        assert_eq!(properties.position, None);
+
+        let geometry = DrawGeometry::new().rectangle(&Rect::from(0.0, 0.0).sized(10.0, 10.0));
+        let request = Request::DrawGeometry {
+            id: "box".to_owned(),
+            geometry,
+            stroke: Stroke {
+                color: Color::WHITE,
+                width: 1.0,
+            },
+            line_style: Default::default(),
+            properties: Properties {
+                z_index: Some(2),
+                ..Default::default()
+            },
+        };
+        let json = serde_json::to_string(&request).unwrap();
+        assert_eq!(serde_json::from_str::<Request>(&json).unwrap(), request);
+
+        let update: Request =
+            serde_json::from_str(r#"{"Update": {"id": "box", "position": {"x": 5, "y": 6}}}"#)
+                .unwrap();
+        assert_eq!(
+            update,
+            Request::Update {
+                id: "box".to_owned(),
+                properties: Properties {
+                    position: Some(Point::new(5.0, 6.0)),
+                    ..Default::default()
+                },
+            }
+        );
+        assert_eq!(
+            serde_json::from_str::<Request>(r#""Clear""#).unwrap(),
+            Request::Clear
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_socket() {
+        use std::os::unix::net::UnixStream;
+        let path = std::env::temp_dir().join(format!("server-test-{}.sock", std::process::id()));
+        let mut endpoint = transport::Endpoint::bind(&path).unwrap();
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let client = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut stream = UnixStream::connect(&path).unwrap();
+                stream
+                    .write_all(b"{\"Remove\": {\"id\": \"a\"}}\n\n\"Clear\"\nnonsense\n")
+                    .unwrap();
+                stream.shutdown(std::net::Shutdown::Write).unwrap();
+                let mut lines = vec![];
+                for line in BufReader::new(stream).lines() {
+                    lines.push(serde_json::from_str::<Response>(&line.unwrap()).unwrap());
+                }
+                lines
+            }
+        });
+
+        let connection = endpoint.accept().unwrap();
+        let mut handled = vec![];
+        serve(&connection, |request| {
+            handled.push(request.clone());
+            match request {
+                Request::Remove { .. } => Err("no visual".into()),
+                _ => Ok(()),
+            }
+        })
+        .unwrap();
+        drop(connection);
+
+        let responses = client.join().unwrap();
+        assert_eq!(handled.len(), 2);
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0], Response::Error("no visual".to_owned()));
+        assert_eq!(responses[1], Response::Ok);
+        assert!(matches!(&responses[2], Response::Error(e) if e.starts_with("invalid request")));
+
+        // A second server can't take over the address.
+        assert!(transport::Endpoint::bind(&path).is_err());
+        drop(endpoint);
+        assert!(!path.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_serve_line_limit() {
+        let (server, mut client) = std::os::unix::net::UnixStream::pair().unwrap();
+        let writer = std::thread::spawn(move || {
+            let mut data = "\"Clear\"\n".to_owned().into_bytes();
+            data.resize(data.len() + MAX_LINE_LENGTH as usize + 1, b' ');
+            data.extend(b"\n\"Clear\"\n");
+            // The server stops reading halfway, so the rest may not be accepted.
+            let _ = client.write_all(&data);
+            let _ = client.shutdown(std::net::Shutdown::Write);
+            let mut responses = vec![];
+            for line in BufReader::new(client).lines() {
+                responses.push(serde_json::from_str::<Response>(&line.unwrap()).unwrap());
+            }
+            responses
+        });
+
+        let mut handled = 0;
+        let result = serve(&server, |_| {
+            handled += 1;
+            Ok(())
+        });
+        drop(server);
+        assert!(result.is_err());
+        assert_eq!(handled, 1);
+
+        let responses = writer.join().unwrap();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0], Response::Ok);
+        assert!(matches!(responses[1], Response::Error(_)));
+    }
+}
@@ -0,0 +1,44 @@
+use crate::Error;
+
+use resvg::{tiny_skia, usvg};
+use std::sync::{Arc, OnceLock};
+
+/// The system fonts are only loaded once, this is slow and they're only needed for svg files with text.
+fn font_database() -> Arc<usvg::fontdb::Database> {
+    static FONTS: OnceLock<Arc<usvg::fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fontdb = usvg::fontdb::Database::new();
+            fontdb.load_system_fonts();
+            Arc::new(fontdb)
+        })
+        .clone()
+}
+
+/// Rasterize an svg file on the cpu.
+///
+/// The document is scaled to fill `width` by `height`, the returned buffer holds premultiplied RGBA pixels.
+pub fn rasterize<P: AsRef<std::path::Path>>(
+    path: P,
+    width: u32,
+    height: u32,
+) -> Result<Vec<u8>, Error> {
+    let path = path.as_ref();
+    let data = std::fs::read(path)?;
+    let options = usvg::Options {
+        // Relative references to images are resolved from the svg's directory.
+        resources_dir: path.parent().map(|p| p.to_owned()),
+        fontdb: font_database(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_data(&data, &options)?;
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or("invalid svg texture size")?;
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(&tree, transform, &mut pixmap.as_mut());
+    Ok(pixmap.take())
+}
@@ -0,0 +1,301 @@
+//! The visual tree shared by the backends; groups hold visuals and other groups.
+//!
+//! Every node has a z-index, visibility, opacity, tint and transform, those of a group apply to everything below it.
+//! Children are kept in drawing order, sorted by z-index; among equal z-indices the node that was added or changed
+//! most recently is on top.
+
+use crate::{Color, Error, Transform2D};
+use std::collections::HashMap;
+
+/// Identifies a node in the visual tree.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct NodeId(usize);
+
+impl NodeId {
+    pub const ROOT: NodeId = NodeId(0);
+}
+
+pub struct Node<T> {
+    pub parent: Option<NodeId>,
+    /// In drawing order, the last child is on top.
+    pub children: Vec<NodeId>,
+    pub z_index: i32,
+    pub visible: bool,
+    pub opacity: f32,
+    pub transform: Transform2D,
+    /// Multiplied into the colors of the content.
+    pub tint: Color,
+    pub data: T,
+}
+
+/// The properties of a node combined with those of all groups it is in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Composed {
+    pub transform: Transform2D,
+    pub opacity: f32,
+    pub tint: Color,
+}
+
+impl Composed {
+    const IDENTITY: Composed = Composed {
+        transform: Transform2D::IDENTITY,
+        opacity: 1.0,
+        tint: Color::WHITE,
+    };
+}
+
+/// The properties of a single node that are set directly, without those of its groups.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Appearance {
+    pub z_index: i32,
+    pub visible: bool,
+    pub opacity: f32,
+    pub tint: Color,
+}
+
+impl<T> Node<T> {
+    pub fn appearance(&self) -> Appearance {
+        Appearance {
+            z_index: self.z_index,
+            visible: self.visible,
+            opacity: self.opacity,
+            tint: self.tint,
+        }
+    }
+
+    fn new(parent: Option<NodeId>, data: T) -> Self {
+        Self {
+            parent,
+            children: vec![],
+            z_index: 0,
+            visible: true,
+            opacity: 1.0,
+            transform: Transform2D::IDENTITY,
+            tint: Color::WHITE,
+            data,
+        }
+    }
+}
+
+pub struct Tree<T> {
+    nodes: HashMap<NodeId, Node<T>>,
+    next: usize,
+}
+
+impl<T> Tree<T> {
+    pub fn new(root: T) -> Self {
+        let mut nodes = HashMap::new();
+        nodes.insert(NodeId::ROOT, Node::new(None, root));
+        Self { nodes, next: 1 }
+    }
+
+    pub fn get(&self, id: NodeId) -> Option<&Node<T>> {
+        self.nodes.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: NodeId) -> Option<&mut Node<T>> {
+        self.nodes.get_mut(&id)
+    }
+
+    /// Every node in no particular order, hidden ones included.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node<T>> {
+        self.nodes.values()
+    }
+
+    fn node(&self, id: NodeId) -> Result<&Node<T>, Error> {
+        self.nodes
+            .get(&id)
+            .ok_or_else(|| format!("visual {id:?} does not exist").into())
+    }
+
+    /// Add a node on top of the children of `parent` with the same z-index.
+    pub fn insert(&mut self, parent: NodeId, data: T) -> Result<NodeId, Error> {
+        self.node(parent)?;
+        let id = NodeId(self.next);
+        self.next += 1;
+        self.nodes.insert(id, Node::new(Some(parent), data));
+        self.link(parent, id);
+        Ok(id)
+    }
+
+    /// Remove a node and everything below it, returns the removed nodes with the requested node first.
+    ///
+    /// Removing a node that no longer exists does nothing, the root can't be removed.
+    pub fn remove(&mut self, id: NodeId) -> Vec<(NodeId, Node<T>)> {
+        if id == NodeId::ROOT {
+            return vec![];
+        }
+        let Some(node) = self.nodes.get(&id) else {
+            return vec![];
+        };
+        if let Some(parent) = node.parent {
+            self.unlink(parent, id);
+        }
+        let mut removed = vec![];
+        let mut pending = vec![id];
+        while let Some(current) = pending.pop() {
+            if let Some(node) = self.nodes.remove(&current) {
+                pending.extend(node.children.iter().copied());
+                removed.push((current, node));
+            }
+        }
+        removed
+    }
+
+    /// Move a node to another parent, it ends up on top of the children with the same z-index.
+    pub fn set_parent(&mut self, id: NodeId, parent: NodeId) -> Result<(), Error> {
+        self.node(parent)?;
+        let old = self.node(id)?.parent.ok_or("the root can't be moved")?;
+        // Walk up from the new parent, finding the node there would create a cycle.
+        let mut current = Some(parent);
+        while let Some(ancestor) = current {
+            if ancestor == id {
+                return Err("a group can't be moved into itself".into());
+            }
+            current = self.nodes[&ancestor].parent;
+        }
+        self.unlink(old, id);
+        self.nodes.get_mut(&id).unwrap().parent = Some(parent);
+        self.link(parent, id);
+        Ok(())
+    }
+
+    /// Change the z-index, the node ends up on top of its siblings with the same z-index.
+    pub fn set_z_index(&mut self, id: NodeId, z_index: i32) -> Result<(), Error> {
+        let parent = self.node(id)?.parent;
+        self.nodes.get_mut(&id).unwrap().z_index = z_index;
+        if let Some(parent) = parent {
+            self.unlink(parent, id);
+            self.link(parent, id);
+        }
+        Ok(())
+    }
+
+    fn link(&mut self, parent: NodeId, id: NodeId) {
+        let z_index = self.nodes[&id].z_index;
+        let children = &self.nodes[&parent].children;
+        let position = children
+            .iter()
+            .position(|c| self.nodes[c].z_index > z_index)
+            .unwrap_or(children.len());
+        self.nodes
+            .get_mut(&parent)
+            .unwrap()
+            .children
+            .insert(position, id);
+    }
+
+    fn unlink(&mut self, parent: NodeId, id: NodeId) {
+        if let Some(node) = self.nodes.get_mut(&parent) {
+            node.children.retain(|c| *c != id);
+        }
+    }
+
+    /// Visit the visible nodes depth first in drawing order, with their accumulated properties.
+    ///
+    /// Only the X11 backend composes the tree itself, DirectComposition does this for us.
+    #[cfg_attr(windows, allow(dead_code))]
+    pub fn walk<F: FnMut(NodeId, &Node<T>, &Composed)>(&self, mut f: F) {
+        self.walk_from(NodeId::ROOT, &Composed::IDENTITY, &mut f);
+    }
+
+    #[cfg_attr(windows, allow(dead_code))]
+    fn walk_from<F: FnMut(NodeId, &Node<T>, &Composed)>(
+        &self,
+        id: NodeId,
+        parent: &Composed,
+        f: &mut F,
+    ) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        if !node.visible {
+            return;
+        }
+        let composed = Composed {
+            transform: node.transform.then(&parent.transform),
+            opacity: node.opacity * parent.opacity,
+            tint: node.tint.multiply(&parent.tint),
+        };
+        f(id, node, &composed);
+        for child in node.children.iter() {
+            self.walk_from(*child, &composed, f);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Point;
+
+    fn visited(tree: &Tree<&'static str>) -> Vec<&'static str> {
+        let mut names = vec![];
+        tree.walk(|_, node, _| names.push(node.data));
+        names
+    }
+
+    #[test]
+    fn test_tree_order_and_removal() {
+        let mut tree = Tree::new("root");
+        let a = tree.insert(NodeId::ROOT, "a").unwrap();
+        let group = tree.insert(NodeId::ROOT, "group").unwrap();
+        let b = tree.insert(group, "b").unwrap();
+        let c = tree.insert(group, "c").unwrap();
+        assert_eq!(visited(&tree), ["root", "a", "group", "b", "c"]);
+
+        // A higher z-index moves a node to the top, a lower one below its siblings.
+        tree.set_z_index(a, 1).unwrap();
+        tree.set_z_index(c, -1).unwrap();
+        assert_eq!(visited(&tree), ["root", "group", "c", "b", "a"]);
+
+        tree.set_parent(a, group).unwrap();
+        assert_eq!(tree.get(group).unwrap().children, [c, b, a]);
+        assert!(tree.set_parent(group, b).is_err());
+
+        let removed: Vec<NodeId> = tree.remove(group).into_iter().map(|(id, _)| id).collect();
+        assert_eq!(removed.len(), 4);
+        assert_eq!(removed[0], group);
+        assert!(tree.get(b).is_none());
+        assert!(tree.remove(b).is_empty());
+        assert!(tree.insert(b, "d").is_err());
+        assert_eq!(visited(&tree), ["root"]);
+    }
+
+    #[test]
+    fn test_tree_walk_accumulates() {
+        let mut tree = Tree::new("root");
+        let outer = tree.insert(NodeId::ROOT, "outer").unwrap();
+        let inner = tree.insert(outer, "inner").unwrap();
+        let leaf = tree.insert(inner, "leaf").unwrap();
+        {
+            let node = tree.get_mut(outer).unwrap();
+            node.transform = Transform2D::translation(10.0, 0.0);
+            node.opacity = 0.5;
+            node.tint = Color::rgb(255, 0, 255);
+        }
+        {
+            let node = tree.get_mut(inner).unwrap();
+            node.transform = Transform2D::scale(2.0, 2.0);
+            node.opacity = 0.5;
+            node.tint = Color::rgba(255, 255, 0, 51);
+        }
+        let mut result = None;
+        tree.walk(|id, _, composed| {
+            if id == leaf {
+                result = Some(*composed);
+            }
+        });
+        let composed = result.unwrap();
+        // The inner transform applies first, then the outer.
+        assert_eq!(
+            composed.transform.transform_point(&Point::new(1.0, 1.0)),
+            Point::new(12.0, 2.0)
+        );
+        assert_eq!(composed.opacity, 0.25);
+        assert_eq!(composed.tint, Color::rgba(255, 0, 0, 51));
+
+        tree.get_mut(inner).unwrap().visible = false;
+        assert_eq!(visited(&tree), ["root", "outer"]);
+    }
+}
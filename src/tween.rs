@@ -0,0 +1,456 @@
+//! Animating the properties of drawn visuals over time with easing curves, driven by the overlay's scheduler.
+//!
+//! Both backends are driven the same way, so repeating, yoyo and completion callbacks behave identically.
+
+use crate::scheduler::TaskId;
+use crate::{Color, Error, Overlay, Point, VisualInner, VisualToken};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// Time between updates of a running tween.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Maps the linear progress of a tween in `[0, 1]` to the progress of the property.
+///
+/// See <https://easings.net> for what these look like.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InSine,
+    OutSine,
+    InOutSine,
+    InExpo,
+    OutExpo,
+    InOutExpo,
+    /// Overshoots the end value a little before settling.
+    OutBack,
+    OutBounce,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        use std::f32::consts::PI;
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::InQuad => t * t,
+            Easing::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOutQuad => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::InCubic => t * t * t,
+            Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::InOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::InSine => 1.0 - (t * PI / 2.0).cos(),
+            Easing::OutSine => (t * PI / 2.0).sin(),
+            Easing::InOutSine => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::InExpo => {
+                if t == 0.0 {
+                    0.0
+                } else {
+                    2.0f32.powf(10.0 * t - 10.0)
+                }
+            }
+            Easing::OutExpo => {
+                if t == 1.0 {
+                    1.0
+                } else {
+                    1.0 - 2.0f32.powf(-10.0 * t)
+                }
+            }
+            Easing::InOutExpo => {
+                if t == 0.0 || t == 1.0 {
+                    t
+                } else if t < 0.5 {
+                    2.0f32.powf(20.0 * t - 10.0) / 2.0
+                } else {
+                    (2.0 - 2.0f32.powf(-20.0 * t + 10.0)) / 2.0
+                }
+            }
+            Easing::OutBack => {
+                const C1: f32 = 1.70158;
+                const C3: f32 = C1 + 1.0;
+                1.0 + C3 * (t - 1.0).powi(3) + C1 * (t - 1.0).powi(2)
+            }
+            Easing::OutBounce => {
+                const N1: f32 = 7.5625;
+                const D1: f32 = 2.75;
+                if t < 1.0 / D1 {
+                    N1 * t * t
+                } else if t < 2.0 / D1 {
+                    let t = t - 1.5 / D1;
+                    N1 * t * t + 0.75
+                } else if t < 2.5 / D1 {
+                    let t = t - 2.25 / D1;
+                    N1 * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / D1;
+                    N1 * t * t + 0.984375
+                }
+            }
+        }
+    }
+}
+
+/// The property of a visual a tween changes, see the matching setters on [`VisualToken`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Property {
+    /// A number, between 0.0 and 1.0.
+    Opacity,
+    /// A point, the offset from where the visual was drawn.
+    Position,
+    /// A number, the uniform scale about the pivot.
+    Scale,
+    /// A number, degrees about the pivot.
+    Rotation,
+    /// A color, the tint multiplied into the visual's colors.
+    Color,
+}
+
+/// The start or end value of a tween.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Value {
+    Number(f32),
+    Point(Point),
+    Color(Color),
+}
+
+impl From<f32> for Value {
+    fn from(v: f32) -> Self {
+        Value::Number(v)
+    }
+}
+impl From<Point> for Value {
+    fn from(v: Point) -> Self {
+        Value::Point(v)
+    }
+}
+impl From<Color> for Value {
+    fn from(v: Color) -> Self {
+        Value::Color(v)
+    }
+}
+
+impl Value {
+    /// The value between `self` at 0.0 and `other` at 1.0, numbers and points extrapolate outside that range.
+    fn lerp(&self, other: &Value, t: f32) -> Option<Value> {
+        Some(match (self, other) {
+            (Value::Number(a), Value::Number(b)) => Value::Number(a + (b - a) * t),
+            (Value::Point(a), Value::Point(b)) => Value::Point(*a + (*b - *a) * t),
+            (Value::Color(a), Value::Color(b)) => Value::Color(a.lerp(b, t)),
+            _ => return None,
+        })
+    }
+}
+
+/// Changes a property of a visual from one value to another over time.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tween {
+    pub property: Property,
+    pub from: Value,
+    pub to: Value,
+    /// The duration of a single play from `from` to `to`.
+    pub duration: Duration,
+    pub easing: Easing,
+    /// Number of plays, `None` repeats forever.
+    pub loops: Option<u32>,
+    /// Every other play runs backwards, from `to` to `from`.
+    pub yoyo: bool,
+    /// Time before the first play starts, the visual shows `from` during it.
+    pub delay: Duration,
+}
+
+impl Tween {
+    /// A tween that plays once.
+    pub fn new<F: Into<Value>, T: Into<Value>>(
+        property: Property,
+        from: F,
+        to: T,
+        duration: Duration,
+        easing: Easing,
+    ) -> Self {
+        Self {
+            property,
+            from: from.into(),
+            to: to.into(),
+            duration,
+            easing,
+            loops: Some(1),
+            yoyo: false,
+            delay: Duration::ZERO,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let valid = match self.property {
+            Property::Opacity | Property::Scale | Property::Rotation => {
+                matches!((self.from, self.to), (Value::Number(_), Value::Number(_)))
+            }
+            Property::Position => {
+                matches!((self.from, self.to), (Value::Point(_), Value::Point(_)))
+            }
+            Property::Color => matches!((self.from, self.to), (Value::Color(_), Value::Color(_))),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "values {:?} and {:?} don't fit {:?}",
+                self.from, self.to, self.property
+            )
+            .into())
+        }
+    }
+
+    /// The value at `elapsed` since the tween started and whether the tween is finished.
+    fn value_at(&self, elapsed: Duration) -> (Value, bool) {
+        let (t, finished) = progress(
+            elapsed.saturating_sub(self.delay),
+            self.duration,
+            self.loops,
+            self.yoyo,
+        );
+        let value = self
+            .from
+            .lerp(&self.to, self.easing.apply(t))
+            .expect("tween is validated");
+        (value, finished)
+    }
+}
+
+/// The linear progress within the current play and whether all plays are done.
+fn progress(elapsed: Duration, duration: Duration, loops: Option<u32>, yoyo: bool) -> (f32, bool) {
+    let backwards = |play: u64| yoyo && play % 2 == 1;
+    let end = |play: u64| if backwards(play) { 0.0 } else { 1.0 };
+    if duration.is_zero() {
+        let plays = loops.unwrap_or(1).max(1) as u64;
+        return (end(plays - 1), true);
+    }
+    let play = (elapsed.as_nanos() / duration.as_nanos()) as u64;
+    if let Some(loops) = loops {
+        let loops = loops.max(1) as u64;
+        if play >= loops {
+            return (end(loops - 1), true);
+        }
+    }
+    let within = elapsed.as_secs_f64() / duration.as_secs_f64() - play as f64;
+    let t = within as f32;
+    (if backwards(play) { 1.0 - t } else { t }, false)
+}
+
+type Callback = Box<dyn FnOnce(&Overlay) + Send>;
+
+#[derive(Default)]
+struct Progress {
+    finished: bool,
+    on_complete: Option<Callback>,
+}
+
+struct TweenState {
+    /// Weak so a detached tween doesn't keep the visual alive.
+    visual: Weak<VisualInner>,
+    tween: Tween,
+    start: Instant,
+    /// Never held while changing the visual, that locks the overlay.
+    progress: Mutex<Progress>,
+}
+
+impl TweenState {
+    /// Apply the value for `now`, returns whether the tween is finished.
+    fn update(&self, now: Instant) -> Result<bool, Error> {
+        let visual = VisualToken {
+            inner: self.visual.upgrade().ok_or("the visual was removed")?,
+        };
+        let (value, finished) = self
+            .tween
+            .value_at(now.saturating_duration_since(self.start));
+        match (self.tween.property, value) {
+            (Property::Opacity, Value::Number(v)) => visual.set_opacity(v)?,
+            (Property::Scale, Value::Number(v)) => visual.set_scale(v)?,
+            (Property::Rotation, Value::Number(v)) => visual.set_rotation(v)?,
+            (Property::Position, Value::Point(p)) => visual.set_position(&p)?,
+            (Property::Color, Value::Color(c)) => visual.set_tint(&c)?,
+            _ => unreachable!("tween is validated"),
+        }
+        self.progress.lock().finished = finished;
+        Ok(finished)
+    }
+}
+
+/// A running tween, it stops where it is when the token is dropped unless it was detached.
+pub struct TweenToken {
+    overlay: Overlay,
+    state: Arc<TweenState>,
+    /// `None` once detached.
+    task: Option<TaskId>,
+}
+
+impl std::fmt::Debug for TweenToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "TweenToken {:?}", self.state.tween.property)
+    }
+}
+
+impl TweenToken {
+    pub(crate) fn new(visual: &VisualToken, tween: &Tween) -> Result<TweenToken, Error> {
+        tween.validate()?;
+        let overlay = visual.overlay().ok_or("the overlay no longer exists")?;
+        let now = Instant::now();
+        let state = Arc::new(TweenState {
+            visual: Arc::downgrade(&visual.inner),
+            tween: *tween,
+            start: now,
+            progress: Default::default(),
+        });
+        if state.update(now)? {
+            return Ok(TweenToken {
+                overlay,
+                state,
+                task: None,
+            });
+        }
+        // The task holds on to the state so a detached tween keeps running.
+        let task_state = state.clone();
+        let task = overlay.scheduler.schedule(
+            now + FRAME_INTERVAL,
+            Box::new(move |overlay, now| {
+                match task_state.update(now) {
+                    Ok(false) => return Some(now + FRAME_INTERVAL),
+                    Ok(true) => {
+                        let callback = task_state.progress.lock().on_complete.take();
+                        if let Some(callback) = callback {
+                            callback(overlay);
+                        }
+                    }
+                    // The visual can't be changed anymore, stop without calling back.
+                    Err(_) => {}
+                }
+                None
+            }),
+        );
+        Ok(TweenToken {
+            overlay,
+            state,
+            task: Some(task),
+        })
+    }
+
+    /// Call `f` once the last play finishes, right away if it already has. Not called if the tween is stopped or
+    /// its visual is removed.
+    ///
+    /// The callback runs on the overlay's scheduler thread, it gets the overlay to draw on.
+    pub fn on_complete<F: FnOnce(&Overlay) + Send + 'static>(&self, f: F) {
+        let mut progress = self.state.progress.lock();
+        if progress.finished {
+            drop(progress);
+            f(&self.overlay);
+        } else {
+            progress.on_complete = Some(Box::new(f));
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.progress.lock().finished
+    }
+
+    /// Let the tween run to its end without holding on to the token, it also stops when its visual is removed.
+    pub fn detach(mut self) {
+        self.task = None;
+    }
+}
+
+impl Drop for TweenToken {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            self.overlay.scheduler.cancel(task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing_ends() {
+        let all = [
+            Easing::Linear,
+            Easing::InQuad,
+            Easing::OutQuad,
+            Easing::InOutQuad,
+            Easing::InCubic,
+            Easing::OutCubic,
+            Easing::InOutCubic,
+            Easing::InSine,
+            Easing::OutSine,
+            Easing::InOutSine,
+            Easing::InExpo,
+            Easing::OutExpo,
+            Easing::InOutExpo,
+            Easing::OutBack,
+            Easing::OutBounce,
+        ];
+        for easing in all {
+            assert!(easing.apply(0.0).abs() < 1e-5, "{easing:?}");
+            assert!((easing.apply(1.0) - 1.0).abs() < 1e-5, "{easing:?}");
+        }
+        assert!(Easing::OutCubic.apply(0.5) > 0.5);
+        assert!(Easing::InCubic.apply(0.5) < 0.5);
+        assert!(Easing::OutBack.apply(0.8) > 1.0);
+    }
+
+    #[test]
+    fn test_tween_progress() {
+        let ms = Duration::from_millis;
+        let near = |(t, f): (f32, bool), (et, ef): (f32, bool)| (t - et).abs() < 1e-4 && f == ef;
+        assert!(near(
+            progress(ms(25), ms(100), Some(1), false),
+            (0.25, false)
+        ));
+        assert!(near(
+            progress(ms(150), ms(100), Some(1), false),
+            (1.0, true)
+        ));
+        // Yoyo runs the second play backwards and ends where it started.
+        assert!(near(
+            progress(ms(125), ms(100), Some(2), true),
+            (0.75, false)
+        ));
+        assert!(near(progress(ms(250), ms(100), Some(2), true), (0.0, true)));
+        assert!(near(
+            progress(ms(1025), ms(100), None, false),
+            (0.25, false)
+        ));
+
+        let tween = Tween {
+            delay: ms(50),
+            ..Tween::new(Property::Opacity, 1.0, 0.0, ms(100), Easing::Linear)
+        };
+        assert_eq!(tween.value_at(ms(10)), (Value::Number(1.0), false));
+        assert_eq!(tween.value_at(ms(100)), (Value::Number(0.5), false));
+        assert_eq!(tween.value_at(ms(200)), (Value::Number(0.0), true));
+        let mismatched = Tween::new(Property::Position, 1.0, 0.0, ms(100), Easing::Linear);
+        assert!(mismatched.validate().is_err());
+    }
+}
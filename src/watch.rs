@@ -0,0 +1,160 @@
+//! Watching files for changes, to update scenes and styles while the overlay is running.
+
+use crate::{Error, LoadedScene, Overlay, Scene, WeakOverlay};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use parking_lot::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Editors often save in several steps, a change is reported once the file has been quiet for this long.
+const SETTLE: Duration = Duration::from_millis(50);
+
+/// Calls a function whenever a file changes, until it is dropped.
+pub struct FileWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+impl std::fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "FileWatcher")
+    }
+}
+
+impl FileWatcher {
+    /// Call `f` on a separate thread after the file at `path` is written, created or replaced.
+    ///
+    /// The directory is watched instead of the file itself, so editors that save by replacing the file are followed.
+    pub fn new<P: AsRef<Path>, F: FnMut() + Send + 'static>(
+        path: P,
+        mut f: F,
+    ) -> Result<FileWatcher, Error> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .ok_or_else(|| format!("{} isn't a file", path.display()))?
+            .to_owned();
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (sender, receiver) = std::sync::mpsc::channel::<()>();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                let Ok(event) = event else {
+                    return;
+                };
+                let relevant = matches!(
+                    event.kind,
+                    EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any
+                );
+                if relevant && event.paths.iter().any(|p| p.file_name() == Some(&name)) {
+                    let _ = sender.send(());
+                }
+            })?;
+        watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+
+        // The sender is owned by the watcher, dropping it ends this thread.
+        std::thread::Builder::new()
+            .name("overlay-file-watcher".to_owned())
+            .spawn(move || {
+                while receiver.recv().is_ok() {
+                    loop {
+                        match receiver.recv_timeout(SETTLE) {
+                            Ok(()) => continue,
+                            Err(RecvTimeoutError::Timeout) => break,
+                            Err(RecvTimeoutError::Disconnected) => return,
+                        }
+                    }
+                    f();
+                }
+            })?;
+        Ok(FileWatcher { _watcher: watcher })
+    }
+}
+
+/// A scene file that is drawn and kept up to date with the file, see [`LoadedScene::update`].
+///
+/// A file that fails to load or draw leaves the scene as it was, the error is printed and the next change is
+/// picked up as usual.
+pub struct SceneWatcher {
+    overlay: WeakOverlay,
+    scene: Arc<Mutex<LoadedScene>>,
+    _watcher: FileWatcher,
+}
+
+impl std::fmt::Debug for SceneWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "SceneWatcher")
+    }
+}
+
+impl SceneWatcher {
+    /// Load and draw the scene, this fails if the file can't be loaded now.
+    pub fn new<P: AsRef<Path>>(overlay: &Overlay, path: P) -> Result<SceneWatcher, Error> {
+        let path = path.as_ref().to_path_buf();
+        let scene = Arc::new(Mutex::new(overlay.load_scene(&path)?));
+        let weak_overlay = overlay.downgrade();
+        let weak_scene = Arc::downgrade(&scene);
+        let watched = path.clone();
+        let watcher = FileWatcher::new(&watched, move || {
+            let (Some(overlay), Some(scene)) = (weak_overlay.upgrade(), weak_scene.upgrade())
+            else {
+                return;
+            };
+            let result = Scene::load(&path).and_then(|new| {
+                // The overlay is locked before the scene, like everywhere else.
+                let _guard = overlay.overlay.lock();
+                scene.lock().update(&new)
+            });
+            if let Err(e) = result {
+                eprintln!("failed to reload scene {}: {e}", path.display());
+            }
+        })?;
+        Ok(SceneWatcher {
+            overlay: overlay.downgrade(),
+            scene,
+            _watcher: watcher,
+        })
+    }
+
+    /// Use the current scene, for example to get the token of an item; reloads wait until this returns.
+    pub fn with_scene<R, F: FnOnce(&LoadedScene) -> R>(&self, f: F) -> R {
+        let overlay = self.overlay.upgrade();
+        let _guard = overlay.as_ref().map(|o| o.overlay.lock());
+        f(&self.scene.lock())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_watcher() {
+        let dir = std::env::temp_dir().join(format!("watch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("style.toml");
+        std::fs::write(&file, "a = 1").unwrap();
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let watcher = FileWatcher::new(&file, move || {
+            let _ = sender.send(());
+        })
+        .unwrap();
+        // Other files in the directory are ignored.
+        std::fs::write(dir.join("other.toml"), "b = 1").unwrap();
+        assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+        // Replacing the file like editors do is followed.
+        let temporary = dir.join("style.toml.tmp");
+        std::fs::write(&temporary, "a = 2").unwrap();
+        std::fs::rename(&temporary, &file).unwrap();
+        assert!(receiver.recv_timeout(Duration::from_secs(5)).is_ok());
+
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
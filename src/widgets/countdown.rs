@@ -0,0 +1,497 @@
+//! A countdown to a deadline, shown as text with an optional bar or ring that empties as time runs out.
+
+use super::{arc, segment};
+use crate::scheduler::TaskId;
+use crate::{
+    Color, Error, Group, LineStyle, Overlay, Point, PreparedFont, Rect, Stroke, VisualToken,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// Time between updates while an indicator is shown, the text only changes when the displayed value changes.
+const INDICATOR_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Shows how much of the countdown is left.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Indicator {
+    /// A horizontal bar filling `area`, it shrinks towards the left.
+    Bar { area: Rect, background: Color },
+    /// A ring that empties counter clockwise, starting at the top.
+    Radial {
+        center: Point,
+        radius: f32,
+        thickness: f32,
+        background: Color,
+    },
+}
+
+impl Indicator {
+    /// Pixels along the filled part, the indicator is only redrawn when this or its color changes.
+    ///
+    /// Rounded up, so it is only zero once nothing is filled.
+    fn fill_key(&self, fraction: f32) -> i64 {
+        let length = match self {
+            Indicator::Bar { area, .. } => area.width(),
+            Indicator::Radial { radius, .. } => std::f32::consts::TAU * radius,
+        };
+        (fraction.clamp(0.0, 1.0) * length).ceil() as i64
+    }
+}
+
+/// The appearance of a [`Countdown`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CountdownStyle {
+    /// The layout rectangle of the text.
+    pub layout: Rect,
+    /// `h`, `m` and `s` are replaced by hours, minutes and seconds and `t` by fractions of a second; repeating a
+    /// letter pads with zeros or adds digits, so `mm:ss.t` shows `01:23.4`. The largest unit holds everything above
+    /// it, without `h` the minutes go past 59. Any other character is shown as is, a backslash shows the next one as
+    /// is too.
+    pub format: String,
+    pub color: Color,
+    /// Colors used once the remaining time is at or below the duration, the lowest matching one applies. They
+    /// apply to the text and the indicator.
+    pub thresholds: Vec<(Duration, Color)>,
+    pub indicator: Option<Indicator>,
+}
+
+impl Default for CountdownStyle {
+    fn default() -> Self {
+        Self {
+            layout: Rect::from(0.0, 0.0).sized(200.0, 50.0),
+            format: "mm:ss.t".to_owned(),
+            color: Color::WHITE,
+            thresholds: vec![],
+            indicator: None,
+        }
+    }
+}
+
+impl CountdownStyle {
+    fn color(&self, remaining: Duration) -> Color {
+        self.thresholds
+            .iter()
+            .filter(|(at, _)| remaining <= *at)
+            .min_by_key(|(at, _)| *at)
+            .map(|(_, color)| *color)
+            .unwrap_or(self.color)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Field {
+    Hours(usize),
+    Minutes(usize),
+    Seconds(usize),
+    /// The number of digits.
+    Fraction(usize),
+    Literal(char),
+}
+
+fn parse_format(format: &str) -> Vec<Field> {
+    let mut fields: Vec<Field> = vec![];
+    let mut escaped = false;
+    for c in format.chars() {
+        if std::mem::take(&mut escaped) {
+            fields.push(Field::Literal(c));
+            continue;
+        }
+        let field = match (c, fields.last_mut()) {
+            ('\\', _) => {
+                escaped = true;
+                continue;
+            }
+            ('h', Some(Field::Hours(n)))
+            | ('m', Some(Field::Minutes(n)))
+            | ('s', Some(Field::Seconds(n)))
+            | ('t', Some(Field::Fraction(n))) => {
+                *n += 1;
+                continue;
+            }
+            ('h', _) => Field::Hours(1),
+            ('m', _) => Field::Minutes(1),
+            ('s', _) => Field::Seconds(1),
+            ('t', _) => Field::Fraction(1),
+            (c, _) => Field::Literal(c),
+        };
+        fields.push(field);
+    }
+    fields
+}
+
+/// The smallest step the format shows, in nanoseconds.
+fn resolution(fields: &[Field]) -> u128 {
+    const SECOND: u128 = 1_000_000_000;
+    let mut resolution = u128::MAX;
+    for field in fields {
+        let step = match field {
+            Field::Hours(_) => 3600 * SECOND,
+            Field::Minutes(_) => 60 * SECOND,
+            Field::Seconds(_) => SECOND,
+            Field::Fraction(digits) => SECOND / 10u128.pow((*digits).min(9) as u32),
+            Field::Literal(_) => continue,
+        };
+        resolution = resolution.min(step);
+    }
+    if resolution == u128::MAX {
+        SECOND
+    } else {
+        resolution
+    }
+}
+
+/// The text for `remaining`, rounded up to the format's resolution so zero only shows once the time is up.
+///
+/// Also returns how long until the text changes, `None` at zero.
+fn format_remaining(fields: &[Field], remaining: Duration) -> (String, Option<Duration>) {
+    const SECOND: u128 = 1_000_000_000;
+    let step = resolution(fields);
+    let nanos = remaining.as_nanos();
+    let steps = nanos.div_ceil(step);
+    let shown = steps * step;
+    let next_change =
+        (steps > 0).then(|| Duration::from_nanos((nanos - (steps - 1) * step) as u64));
+
+    let has = |f: fn(&Field) -> bool| fields.iter().any(f);
+    let has_hours = has(|f| matches!(f, Field::Hours(_)));
+    let has_minutes = has(|f| matches!(f, Field::Minutes(_)));
+    let mut rest = shown / SECOND;
+    let hours = if has_hours { rest / 3600 } else { 0 };
+    rest -= hours * 3600;
+    let minutes = if has_minutes { rest / 60 } else { 0 };
+    rest -= minutes * 60;
+    let seconds = rest;
+
+    let mut text = String::new();
+    for field in fields {
+        match field {
+            Field::Hours(width) => text += &format!("{hours:0width$}"),
+            Field::Minutes(width) => text += &format!("{minutes:0width$}"),
+            Field::Seconds(width) => text += &format!("{seconds:0width$}"),
+            Field::Fraction(digits) => {
+                let digits = (*digits).min(9);
+                let fraction = (shown % SECOND) / 10u128.pow(9 - digits as u32);
+                text += &format!("{fraction:0digits$}");
+            }
+            Field::Literal(c) => text.push(*c),
+        }
+    }
+    (text, next_change)
+}
+
+type Callback = Box<dyn FnOnce(&Overlay) + Send>;
+
+struct Timing {
+    deadline: Instant,
+    /// The full length of the countdown, the indicator shows the remaining part of it.
+    total: Duration,
+}
+
+#[derive(Default)]
+struct Completion {
+    finished: bool,
+    on_complete: Option<Callback>,
+}
+
+/// The drawn text with its content and color, and the indicator with the key it was drawn for.
+#[derive(Default)]
+struct Visuals {
+    text: Option<(String, Color, VisualToken)>,
+    indicator: Option<((i64, Color), Group)>,
+}
+
+struct Shared {
+    group: Group,
+    font: PreparedFont,
+    style: CountdownStyle,
+    fields: Vec<Field>,
+    timing: Mutex<Timing>,
+    completion: Mutex<Completion>,
+    visuals: Mutex<Visuals>,
+}
+
+impl Shared {
+    /// Redraw what changed for `now`, returns when to update next; `None` once the countdown is done.
+    fn update(&self, overlay: &Overlay, now: Instant) -> Result<Option<Instant>, Error> {
+        let (remaining, total) = {
+            let timing = self.timing.lock();
+            (timing.deadline.saturating_duration_since(now), timing.total)
+        };
+        let (text, next_change) = format_remaining(&self.fields, remaining);
+        let color = self.style.color(remaining);
+        overlay.transaction(|tx| {
+            let mut visuals = self.visuals.lock();
+            let changed = visuals
+                .text
+                .as_ref()
+                .is_none_or(|(t, c, _)| *t != text || *c != color);
+            if changed {
+                let token = tx.draw_text(&text, &self.style.layout, &color, &self.font)?;
+                self.group.add(&token)?;
+                visuals.text = Some((text, color, token));
+            }
+            if let Some(indicator) = &self.style.indicator {
+                let fraction = if total.is_zero() {
+                    0.0
+                } else {
+                    remaining.as_secs_f32() / total.as_secs_f32()
+                };
+                let key = (indicator.fill_key(fraction), color);
+                if visuals.indicator.as_ref().is_none_or(|(k, _)| *k != key) {
+                    let drawn = draw_indicator(tx, indicator, fraction, &color)?;
+                    self.group.add_group(&drawn)?;
+                    visuals.indicator = Some((key, drawn));
+                }
+            }
+            Ok(())
+        })?;
+
+        let callback = {
+            let mut completion = self.completion.lock();
+            let finished = remaining.is_zero();
+            let just_finished = finished && !completion.finished;
+            completion.finished = finished;
+            if just_finished {
+                completion.on_complete.take()
+            } else {
+                None
+            }
+        };
+        if let Some(callback) = callback {
+            callback(overlay);
+        }
+
+        Ok(next_change.map(|next| {
+            // A little past the change, so the new value shows.
+            let next = now + next + Duration::from_millis(1);
+            if self.style.indicator.is_some() {
+                next.min(now + INDICATOR_INTERVAL)
+            } else {
+                next
+            }
+        }))
+    }
+}
+
+/// Draw the indicator with the track in its background color and `fraction` of it in `color`.
+fn draw_indicator(
+    overlay: &Overlay,
+    indicator: &Indicator,
+    fraction: f32,
+    color: &Color,
+) -> Result<Group, Error> {
+    let fraction = fraction.clamp(0.0, 1.0);
+    let (track, filled, width, background) = match indicator {
+        Indicator::Bar { area, background } => {
+            let y = area.center().y;
+            let start = Point::new(area.min.x, y);
+            let end = Point::new(area.min.x + area.width() * fraction, y);
+            (
+                segment(&start, &Point::new(area.max.x, y)),
+                segment(&start, &end),
+                area.height(),
+                background,
+            )
+        }
+        Indicator::Radial {
+            center,
+            radius,
+            thickness,
+            background,
+        } => (
+            arc(center, *radius, 0.0, 360.0),
+            arc(center, *radius, 0.0, 360.0 * fraction),
+            *thickness,
+            background,
+        ),
+    };
+    let group = overlay.create_group()?;
+    let style = LineStyle::default();
+    for (geometry, color, shown) in [
+        (track, background, *background != Color::TRANSPARENT),
+        (filled, color, fraction > 0.0),
+    ] {
+        if shown {
+            let stroke = Stroke {
+                color: *color,
+                width,
+            };
+            group.add(&overlay.draw_geometry(&geometry, &stroke, &style)?)?;
+        }
+    }
+    Ok(group)
+}
+
+/// Text counting down to a deadline, it updates itself until it reaches zero and is removed when dropped.
+///
+/// Geometry isn't drawn on X11 yet, so the indicator only shows on Windows.
+pub struct Countdown {
+    overlay: Overlay,
+    shared: Arc<Shared>,
+    task: Mutex<Option<TaskId>>,
+}
+
+impl std::fmt::Debug for Countdown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Countdown {:?} remaining", self.remaining())
+    }
+}
+
+impl Countdown {
+    pub fn new(
+        overlay: &Overlay,
+        deadline: Instant,
+        font: &PreparedFont,
+        style: &CountdownStyle,
+    ) -> Result<Countdown, Error> {
+        let countdown = Countdown {
+            overlay: overlay.clone(),
+            shared: Arc::new(Shared {
+                group: overlay.create_group()?,
+                font: font.clone(),
+                style: style.clone(),
+                fields: parse_format(&style.format),
+                timing: Mutex::new(Timing {
+                    deadline,
+                    total: deadline.saturating_duration_since(Instant::now()),
+                }),
+                completion: Default::default(),
+                visuals: Default::default(),
+            }),
+            task: Default::default(),
+        };
+        countdown.restart()?;
+        Ok(countdown)
+    }
+
+    /// Redraw for now and schedule the next update.
+    fn restart(&self) -> Result<(), Error> {
+        self.overlay.transaction(|overlay| {
+            let mut task = self.task.lock();
+            if let Some(task) = task.take() {
+                overlay.scheduler.cancel(task);
+            }
+            if let Some(due) = self.shared.update(overlay, Instant::now())? {
+                let weak: Weak<Shared> = Arc::downgrade(&self.shared);
+                *task = Some(overlay.scheduler.schedule(
+                    due,
+                    Box::new(move |overlay, now| {
+                        let shared = weak.upgrade()?;
+                        shared.update(overlay, now).ok().flatten()
+                    }),
+                ));
+            }
+            Ok(())
+        })
+    }
+
+    /// The group holding the countdown's visuals.
+    pub fn group(&self) -> &Group {
+        &self.shared.group
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.shared
+            .timing
+            .lock()
+            .deadline
+            .saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.shared.completion.lock().finished
+    }
+
+    /// Count down to a new deadline, the indicator starts full again.
+    pub fn set_deadline(&self, deadline: Instant) -> Result<(), Error> {
+        {
+            let mut timing = self.shared.timing.lock();
+            timing.deadline = deadline;
+            timing.total = deadline.saturating_duration_since(Instant::now());
+        }
+        self.restart()
+    }
+
+    /// Call `f` when the countdown reaches zero, right away if it already has.
+    ///
+    /// The callback usually runs on the overlay's scheduler thread. To get a channel instead, send on one from the
+    /// callback.
+    pub fn on_complete<F: FnOnce(&Overlay) + Send + 'static>(&self, f: F) {
+        let mut completion = self.shared.completion.lock();
+        if completion.finished {
+            drop(completion);
+            f(&self.overlay);
+        } else {
+            completion.on_complete = Some(Box::new(f));
+        }
+    }
+}
+
+impl Drop for Countdown {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.lock().take() {
+            self.overlay.scheduler.cancel(task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(format: &str, seconds: f64) -> String {
+        format_remaining(&parse_format(format), Duration::from_secs_f64(seconds)).0
+    }
+
+    #[test]
+    fn test_countdown_format() {
+        assert_eq!(format("mm:ss.t", 83.25), "01:23.3");
+        assert_eq!(format("mm:ss.t", 0.0), "00:00.0");
+        // Without hours the minutes hold everything.
+        assert_eq!(format("m:ss", 3725.0), "62:05");
+        assert_eq!(format("h:mm:ss", 3725.0), "1:02:05");
+        // Rounded up, zero only shows at the end.
+        assert_eq!(format("ss", 0.2), "01");
+        assert_eq!(format("s.ttt lef\\t", 1.5), "1.500 left");
+
+        let fields = parse_format("ss.t");
+        let (_, next) = format_remaining(&fields, Duration::from_millis(1234));
+        assert_eq!(next, Some(Duration::from_millis(34)));
+        assert_eq!(format_remaining(&fields, Duration::ZERO).1, None);
+    }
+
+    #[test]
+    fn test_countdown_thresholds() {
+        let style = CountdownStyle {
+            thresholds: vec![
+                (Duration::from_secs(10), Color::rgb(255, 255, 0)),
+                (Duration::from_secs(3), Color::rgb(255, 0, 0)),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(style.color(Duration::from_secs(20)), Color::WHITE);
+        assert_eq!(
+            style.color(Duration::from_secs(10)),
+            Color::rgb(255, 255, 0)
+        );
+        assert_eq!(style.color(Duration::from_secs(1)), Color::rgb(255, 0, 0));
+    }
+
+    #[test]
+    fn test_indicator_fill_key() {
+        let bar = Indicator::Bar {
+            area: Rect::from(0.0, 0.0).sized(100.0, 10.0),
+            background: Color::TRANSPARENT,
+        };
+        assert_eq!(bar.fill_key(0.5), 50);
+        assert_eq!(bar.fill_key(0.502), 51);
+        // Anything left keeps a pixel filled.
+        assert_eq!(bar.fill_key(0.001), 1);
+        assert_eq!(bar.fill_key(0.0), 0);
+        assert_eq!(bar.fill_key(2.0), 100);
+    }
+}
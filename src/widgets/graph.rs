@@ -0,0 +1,496 @@
+//! A scrolling graph of the most recent samples of one or more series, for frame times, latency or telemetry.
+
+use super::segment;
+use crate::scheduler::TaskId;
+use crate::{
+    Color, DrawGeometry, Error, GeometryElement, Group, LineStyle, Overlay, Point, PreparedFont,
+    Rect, Stroke, VisualToken,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+/// Minimum time between redraws, samples pushed in between are drawn together.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How one series of a [`Graph`] is drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SeriesStyle {
+    pub stroke: Stroke,
+    /// The area below the line, transparent to only draw the line.
+    pub fill: Color,
+}
+
+impl Default for SeriesStyle {
+    fn default() -> Self {
+        Self {
+            stroke: Stroke {
+                color: Color::WHITE,
+                width: 1.5,
+            },
+            fill: Color::TRANSPARENT,
+        }
+    }
+}
+
+/// Statistics of the samples in view, one line per series in the color of its stroke.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsLabel {
+    /// Where the first series' line goes, the others follow below it.
+    pub layout: Rect,
+    /// `{min}`, `{max}`, `{avg}` and `{last}` are replaced by the statistics of the series.
+    pub text: String,
+    /// Digits after the decimal point.
+    pub precision: usize,
+}
+
+impl Default for StatsLabel {
+    fn default() -> Self {
+        Self {
+            layout: Rect::from(0.0, 0.0).sized(300.0, 20.0),
+            text: "min {min} max {max} avg {avg}".to_owned(),
+            precision: 1,
+        }
+    }
+}
+
+/// The appearance of a [`Graph`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphStyle {
+    pub area: Rect,
+    /// Samples kept per series, they are spread over the width with the newest on the right.
+    pub capacity: usize,
+    /// The values at the bottom and top, `None` fits the samples in view.
+    pub range: Option<(f32, f32)>,
+    pub series: Vec<SeriesStyle>,
+    /// Drawn behind the graph, transparent to leave it out.
+    pub background: Color,
+    /// Horizontal lines dividing the height in equal parts.
+    pub grid_lines: u32,
+    pub grid: Stroke,
+    pub stats: Option<StatsLabel>,
+}
+
+impl Default for GraphStyle {
+    fn default() -> Self {
+        Self {
+            area: Rect::from(0.0, 0.0).sized(300.0, 100.0),
+            capacity: 120,
+            range: None,
+            series: vec![SeriesStyle::default()],
+            background: Color::rgba(0, 0, 0, 128),
+            grid_lines: 3,
+            grid: Stroke {
+                color: Color::rgba(255, 255, 255, 48),
+                width: 1.0,
+            },
+            stats: None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Stats {
+    min: f32,
+    max: f32,
+    avg: f32,
+    last: f32,
+}
+
+fn stats(samples: &VecDeque<f32>) -> Option<Stats> {
+    let last = *samples.back()?;
+    let (min, max, sum) = samples
+        .iter()
+        .fold((f32::MAX, f32::MIN, 0.0), |(min, max, sum), v| {
+            (min.min(*v), max.max(*v), sum + v)
+        });
+    Some(Stats {
+        min,
+        max,
+        avg: sum / samples.len() as f32,
+        last,
+    })
+}
+
+impl StatsLabel {
+    fn format(&self, stats: &Stats) -> String {
+        let precision = self.precision;
+        self.text
+            .replace("{min}", &format!("{:.precision$}", stats.min))
+            .replace("{max}", &format!("{:.precision$}", stats.max))
+            .replace("{avg}", &format!("{:.precision$}", stats.avg))
+            .replace("{last}", &format!("{:.precision$}", stats.last))
+    }
+}
+
+/// The values at the bottom and top of the graph, fitted to all series unless the style fixes them.
+fn value_range(range: Option<(f32, f32)>, series: &[VecDeque<f32>]) -> (f32, f32) {
+    if let Some(range) = range {
+        return range;
+    }
+    let (low, high) = series
+        .iter()
+        .flatten()
+        .fold((f32::MAX, f32::MIN), |(low, high), v| {
+            (low.min(*v), high.max(*v))
+        });
+    if low > high {
+        (0.0, 1.0)
+    } else if high == low {
+        // A flat line sits in the middle.
+        (low - 0.5, high + 0.5)
+    } else {
+        (low, high)
+    }
+}
+
+/// The samples as points in `area`, the newest at the right edge, values outside the range are clamped to it.
+fn points(area: &Rect, capacity: usize, range: (f32, f32), samples: &VecDeque<f32>) -> Vec<Point> {
+    let step = area.width() / capacity.saturating_sub(1).max(1) as f32;
+    let (low, high) = range;
+    let span = if high == low { 1.0 } else { high - low };
+    let newest = samples.len().saturating_sub(1);
+    samples
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let fraction = ((v - low) / span).clamp(0.0, 1.0);
+            Point::new(
+                area.max.x - (newest - i) as f32 * step,
+                area.max.y - area.height() * fraction,
+            )
+        })
+        .collect()
+}
+
+fn polyline(points: &[Point]) -> Option<DrawGeometry> {
+    let (first, rest) = points.split_first()?;
+    let mut elements = vec![GeometryElement::Start {
+        start: *first,
+        filled: false,
+    }];
+    elements.extend(rest.iter().map(|p| GeometryElement::Line(*p)));
+    elements.push(GeometryElement::End { closed: false });
+    Some(DrawGeometry { elements })
+}
+
+/// The area between the line and `bottom`.
+fn area_below(points: &[Point], bottom: f32) -> Option<DrawGeometry> {
+    let (first, last) = (points.first()?, points.last()?);
+    let mut elements = vec![GeometryElement::Start {
+        start: Point::new(first.x, bottom),
+        filled: true,
+    }];
+    elements.extend(points.iter().map(|p| GeometryElement::Line(*p)));
+    elements.push(GeometryElement::Line(Point::new(last.x, bottom)));
+    elements.push(GeometryElement::End { closed: true });
+    Some(DrawGeometry { elements })
+}
+
+#[derive(Default)]
+struct Samples {
+    series: Vec<VecDeque<f32>>,
+    /// A redraw is scheduled for the pushed samples.
+    scheduled: bool,
+    last_draw: Option<Instant>,
+}
+
+/// The plotted lines and the statistics labels with their text.
+#[derive(Default)]
+struct Drawn {
+    plots: Vec<VisualToken>,
+    stats: Vec<Option<(String, VisualToken)>>,
+}
+
+struct Shared {
+    style: GraphStyle,
+    font: Option<PreparedFont>,
+    /// Above the background and grid, below the statistics.
+    plot: Group,
+    stats: Group,
+    samples: Mutex<Samples>,
+    drawn: Mutex<Drawn>,
+}
+
+impl Shared {
+    fn redraw(&self, overlay: &Overlay, now: Instant) -> Result<(), Error> {
+        let series = {
+            let mut samples = self.samples.lock();
+            samples.scheduled = false;
+            samples.last_draw = Some(now);
+            samples.series.clone()
+        };
+        let style = &self.style;
+        let range = value_range(style.range, &series);
+        let line_style = LineStyle::default();
+        overlay.transaction(|tx| {
+            let mut drawn = self.drawn.lock();
+            let mut plots = vec![];
+            for (samples, series_style) in series.iter().zip(&style.series) {
+                let points = points(&style.area, style.capacity, range, samples);
+                if series_style.fill != Color::TRANSPARENT {
+                    if let Some(area) = area_below(&points, style.area.max.y) {
+                        let stroke = Stroke {
+                            color: series_style.fill,
+                            width: 1.0,
+                        };
+                        plots.push(tx.draw_geometry(&area, &stroke, &line_style)?);
+                    }
+                }
+                if let Some(line) = polyline(&points) {
+                    plots.push(tx.draw_geometry(&line, &series_style.stroke, &line_style)?);
+                }
+            }
+            for token in &plots {
+                self.plot.add(token)?;
+            }
+            drawn.plots = plots;
+
+            if let (Some(label), Some(font)) = (&style.stats, &self.font) {
+                drawn.stats.resize_with(series.len(), || None);
+                let height = label.layout.height();
+                for (i, samples) in series.iter().enumerate() {
+                    let Some(stats) = stats(samples) else {
+                        drawn.stats[i] = None;
+                        continue;
+                    };
+                    let text = label.format(&stats);
+                    if drawn.stats[i].as_ref().is_some_and(|(t, _)| *t == text) {
+                        continue;
+                    }
+                    let layout = Rect {
+                        min: label.layout.min + Point::new(0.0, height * i as f32),
+                        max: label.layout.max + Point::new(0.0, height * i as f32),
+                    };
+                    let color = style.series[i].stroke.color;
+                    let token = tx.draw_text(&text, &layout, &color, font)?;
+                    self.stats.add(&token)?;
+                    drawn.stats[i] = Some((text, token));
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+/// A graph of the most recent samples of one or more series, scrolling left as samples are pushed. Removed when
+/// dropped.
+///
+/// Pushing only stores the sample, the graph redraws itself at most once per frame. Geometry isn't drawn on X11 yet,
+/// so only the statistics show there.
+pub struct Graph {
+    overlay: Overlay,
+    group: Group,
+    /// The background and grid, kept so they stay drawn.
+    _backdrop: Group,
+    shared: Arc<Shared>,
+    task: Mutex<Option<TaskId>>,
+}
+
+impl std::fmt::Debug for Graph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Graph {} series", self.shared.style.series.len())
+    }
+}
+
+impl Graph {
+    /// Create an empty graph, the font is only needed if the style has statistics.
+    pub fn new(
+        overlay: &Overlay,
+        style: &GraphStyle,
+        font: Option<&PreparedFont>,
+    ) -> Result<Graph, Error> {
+        if style.series.is_empty() {
+            return Err("a graph needs at least one series".into());
+        }
+        if style.capacity < 2 {
+            return Err("a graph needs a capacity of at least two samples".into());
+        }
+        if style.stats.is_some() && font.is_none() {
+            return Err("statistics need a font".into());
+        }
+        overlay.transaction(|tx| {
+            let group = tx.create_group()?;
+            let backdrop = tx.create_group()?;
+            let line_style = LineStyle::default();
+            let area = &style.area;
+            if style.background != Color::TRANSPARENT {
+                let mut background = DrawGeometry::new().rectangle(area);
+                if let Some(GeometryElement::Start { filled, .. }) = background.elements.first_mut()
+                {
+                    *filled = true;
+                }
+                let stroke = Stroke {
+                    color: style.background,
+                    width: 1.0,
+                };
+                backdrop.add(&tx.draw_geometry(&background, &stroke, &line_style)?)?;
+            }
+            for i in 1..=style.grid_lines {
+                let y = area.min.y + area.height() * i as f32 / (style.grid_lines + 1) as f32;
+                let line = segment(&Point::new(area.min.x, y), &Point::new(area.max.x, y));
+                backdrop.add(&tx.draw_geometry(&line, &style.grid, &line_style)?)?;
+            }
+            let shared = Shared {
+                style: style.clone(),
+                font: font.cloned(),
+                plot: tx.create_group()?,
+                stats: tx.create_group()?,
+                samples: Mutex::new(Samples {
+                    series: vec![VecDeque::with_capacity(style.capacity); style.series.len()],
+                    ..Default::default()
+                }),
+                drawn: Default::default(),
+            };
+            group.add_group(&backdrop)?;
+            group.add_group(&shared.plot)?;
+            group.add_group(&shared.stats)?;
+            Ok(Graph {
+                overlay: overlay.clone(),
+                group,
+                _backdrop: backdrop,
+                shared: Arc::new(shared),
+                task: Default::default(),
+            })
+        })
+    }
+
+    /// Add a sample to the first series.
+    pub fn push(&self, sample: f32) -> Result<(), Error> {
+        self.push_series(0, sample)
+    }
+
+    /// Add a sample to a series, the oldest one scrolls out once the capacity is reached. Samples that aren't finite
+    /// are ignored.
+    pub fn push_series(&self, series: usize, sample: f32) -> Result<(), Error> {
+        if !sample.is_finite() {
+            return Ok(());
+        }
+        let due = {
+            let mut samples = self.shared.samples.lock();
+            let capacity = self.shared.style.capacity;
+            let buffer = samples
+                .series
+                .get_mut(series)
+                .ok_or_else(|| format!("graph has no series {series}"))?;
+            if buffer.len() == capacity {
+                buffer.pop_front();
+            }
+            buffer.push_back(sample);
+            self.schedule_redraw(&mut samples)
+        };
+        if let Some(due) = due {
+            self.start_redraw(due);
+        }
+        Ok(())
+    }
+
+    /// Remove all samples.
+    pub fn clear(&self) {
+        let due = {
+            let mut samples = self.shared.samples.lock();
+            samples.series.iter_mut().for_each(VecDeque::clear);
+            self.schedule_redraw(&mut samples)
+        };
+        if let Some(due) = due {
+            self.start_redraw(due);
+        }
+    }
+
+    /// The samples of a series, oldest first.
+    pub fn samples(&self, series: usize) -> Vec<f32> {
+        self.shared
+            .samples
+            .lock()
+            .series
+            .get(series)
+            .map(|s| s.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// The group holding the graph's visuals.
+    pub fn group(&self) -> &Group {
+        &self.group
+    }
+
+    /// When to redraw, `None` if a redraw is already scheduled.
+    fn schedule_redraw(&self, samples: &mut Samples) -> Option<Instant> {
+        if samples.scheduled {
+            return None;
+        }
+        samples.scheduled = true;
+        let now = Instant::now();
+        Some(
+            samples
+                .last_draw
+                .map_or(now, |last| (last + FRAME_INTERVAL).max(now)),
+        )
+    }
+
+    fn start_redraw(&self, due: Instant) {
+        let weak: Weak<Shared> = Arc::downgrade(&self.shared);
+        let id = self.overlay.scheduler.schedule(
+            due,
+            Box::new(move |overlay, now| {
+                let shared = weak.upgrade()?;
+                let _ = shared.redraw(overlay, now);
+                None
+            }),
+        );
+        *self.task.lock() = Some(id);
+    }
+}
+
+impl Drop for Graph {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.lock().take() {
+            self.overlay.scheduler.cancel(task);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_points() {
+        let samples: VecDeque<f32> = [0.0, 5.0, 10.0].into_iter().collect();
+        assert_eq!(
+            value_range(None, std::slice::from_ref(&samples)),
+            (0.0, 10.0)
+        );
+        assert_eq!(
+            value_range(Some((0.0, 20.0)), std::slice::from_ref(&samples)),
+            (0.0, 20.0)
+        );
+        assert_eq!(
+            value_range(None, &[[3.0].into_iter().collect()]),
+            (2.5, 3.5)
+        );
+        assert_eq!(value_range(None, &[VecDeque::new()]), (0.0, 1.0));
+
+        let area = Rect::from(0.0, 0.0).sized(100.0, 50.0);
+        let points = points(&area, 11, (0.0, 10.0), &samples);
+        // Newest on the right, ten pixels apart.
+        assert_eq!(points[2], Point::new(100.0, 0.0));
+        assert_eq!(points[1], Point::new(90.0, 25.0));
+        assert_eq!(points[0], Point::new(80.0, 50.0));
+
+        let stats = stats(&samples).unwrap();
+        assert_eq!(
+            (stats.min, stats.max, stats.avg, stats.last),
+            (0.0, 10.0, 5.0, 10.0)
+        );
+        assert_eq!(
+            StatsLabel::default().format(&stats),
+            "min 0.0 max 10.0 avg 5.0"
+        );
+    }
+}
@@ -0,0 +1,519 @@
+//! Progress bars and gauges, showing a value within a range as a bar or an arc.
+
+use super::{arc, segment};
+use crate::{
+    Color, Error, Group, LineStyle, Overlay, Point, PreparedFont, Rect, Stroke, VisualToken,
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Text showing the value of a [`ProgressBar`] or [`Gauge`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Label {
+    pub layout: Rect,
+    /// `{value}` is replaced by the value and `{percent}` by its position in the range in percent.
+    pub text: String,
+    /// Digits after the decimal point.
+    pub precision: usize,
+    pub color: Color,
+}
+
+impl Default for Label {
+    fn default() -> Self {
+        Self {
+            layout: Rect::from(0.0, 0.0).sized(100.0, 30.0),
+            text: "{percent}%".to_owned(),
+            precision: 0,
+            color: Color::WHITE,
+        }
+    }
+}
+
+impl Label {
+    fn format(&self, value: f32, fraction: f32) -> String {
+        let precision = self.precision;
+        self.text
+            .replace("{value}", &format!("{value:.precision$}"))
+            .replace("{percent}", &format!("{:.precision$}", fraction * 100.0))
+    }
+}
+
+/// The direction a [`ProgressBar`] fills in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Default, Serialize, Deserialize)]
+pub enum Orientation {
+    /// Fills from left to right.
+    #[default]
+    Horizontal,
+    /// Fills from bottom to top.
+    Vertical,
+}
+
+/// The appearance of a [`ProgressBar`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProgressBarStyle {
+    pub area: Rect,
+    pub orientation: Orientation,
+    /// Split the bar into this many segments, a segment is lit while any part of it is filled. Zero or one draws a
+    /// continuous bar.
+    pub segments: u32,
+    /// Space between segments in pixels.
+    pub gap: f32,
+    /// The values at the start and end of the bar.
+    pub range: (f32, f32),
+    pub color: Color,
+    /// The color of the unfilled part, transparent to leave it out.
+    pub background: Color,
+    /// Colors used once the value is at or above the threshold, the highest matching one applies.
+    pub thresholds: Vec<(f32, Color)>,
+    pub label: Option<Label>,
+}
+
+impl Default for ProgressBarStyle {
+    fn default() -> Self {
+        Self {
+            area: Rect::from(0.0, 0.0).sized(200.0, 20.0),
+            orientation: Orientation::Horizontal,
+            segments: 0,
+            gap: 2.0,
+            range: (0.0, 1.0),
+            color: Color::WHITE,
+            background: Color::rgba(0, 0, 0, 128),
+            thresholds: vec![],
+            label: None,
+        }
+    }
+}
+
+/// The appearance of a [`Gauge`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GaugeStyle {
+    pub center: Point,
+    /// Radius of the middle of the arc.
+    pub radius: f32,
+    pub thickness: f32,
+    /// Where the arc starts, in degrees clockwise from the top.
+    pub start_angle: f32,
+    /// The angle covered by the full range, positive fills clockwise.
+    pub sweep: f32,
+    /// The values at the start and end of the arc.
+    pub range: (f32, f32),
+    pub color: Color,
+    /// The color of the unfilled part, transparent to leave it out.
+    pub background: Color,
+    /// Colors used once the value is at or above the threshold, the highest matching one applies.
+    pub thresholds: Vec<(f32, Color)>,
+    pub label: Option<Label>,
+}
+
+impl Default for GaugeStyle {
+    fn default() -> Self {
+        Self {
+            center: Point::new(50.0, 50.0),
+            radius: 40.0,
+            thickness: 8.0,
+            start_angle: -135.0,
+            sweep: 270.0,
+            range: (0.0, 1.0),
+            color: Color::WHITE,
+            background: Color::rgba(0, 0, 0, 128),
+            thresholds: vec![],
+            label: None,
+        }
+    }
+}
+
+/// What the bar and the gauge differ in, the shape of the track and the filled part.
+trait Shape {
+    fn stroke_width(&self) -> f32;
+    fn track(&self) -> Vec<crate::DrawGeometry>;
+    fn fill(&self, fraction: f32) -> Vec<crate::DrawGeometry>;
+    /// Changes whenever the drawn fill changes, so unchanged fills aren't redrawn.
+    fn fill_key(&self, fraction: f32) -> i64;
+}
+
+impl ProgressBarStyle {
+    /// The line through the middle of the bar from `from` to `to`, as fractions of its length.
+    fn span(&self, from: f32, to: f32) -> crate::DrawGeometry {
+        let area = &self.area;
+        match self.orientation {
+            Orientation::Horizontal => {
+                let y = area.center().y;
+                let x = |f: f32| area.min.x + area.width() * f;
+                segment(&Point::new(x(from), y), &Point::new(x(to), y))
+            }
+            Orientation::Vertical => {
+                let x = area.center().x;
+                let y = |f: f32| area.max.y - area.height() * f;
+                segment(&Point::new(x, y(from)), &Point::new(x, y(to)))
+            }
+        }
+    }
+
+    fn length(&self) -> f32 {
+        match self.orientation {
+            Orientation::Horizontal => self.area.width(),
+            Orientation::Vertical => self.area.height(),
+        }
+    }
+
+    /// The start and end of every segment as fractions of the length.
+    fn segment_spans(&self) -> Vec<(f32, f32)> {
+        let count = self.segments.max(1);
+        let length = self.length();
+        if count == 1 || length <= 0.0 {
+            return vec![(0.0, 1.0)];
+        }
+        let gap = (self.gap / length).clamp(0.0, 1.0 / count as f32);
+        let size = (1.0 - gap * (count - 1) as f32) / count as f32;
+        (0..count)
+            .map(|i| {
+                let start = i as f32 * (size + gap);
+                (start, start + size)
+            })
+            .collect()
+    }
+
+    fn lit_segments(&self, fraction: f32) -> usize {
+        (fraction * self.segments as f32 - 1e-4).ceil().max(0.0) as usize
+    }
+}
+
+impl Shape for ProgressBarStyle {
+    fn stroke_width(&self) -> f32 {
+        match self.orientation {
+            Orientation::Horizontal => self.area.height(),
+            Orientation::Vertical => self.area.width(),
+        }
+    }
+
+    fn track(&self) -> Vec<crate::DrawGeometry> {
+        self.segment_spans()
+            .into_iter()
+            .map(|(from, to)| self.span(from, to))
+            .collect()
+    }
+
+    fn fill(&self, fraction: f32) -> Vec<crate::DrawGeometry> {
+        if self.segments > 1 {
+            let lit = self.lit_segments(fraction);
+            self.segment_spans()
+                .into_iter()
+                .take(lit)
+                .map(|(from, to)| self.span(from, to))
+                .collect()
+        } else if fraction > 0.0 {
+            vec![self.span(0.0, fraction)]
+        } else {
+            vec![]
+        }
+    }
+
+    fn fill_key(&self, fraction: f32) -> i64 {
+        if self.segments > 1 {
+            self.lit_segments(fraction) as i64
+        } else {
+            (fraction * self.length()).round() as i64
+        }
+    }
+}
+
+impl Shape for GaugeStyle {
+    fn stroke_width(&self) -> f32 {
+        self.thickness
+    }
+
+    fn track(&self) -> Vec<crate::DrawGeometry> {
+        vec![arc(&self.center, self.radius, self.start_angle, self.sweep)]
+    }
+
+    fn fill(&self, fraction: f32) -> Vec<crate::DrawGeometry> {
+        if fraction > 0.0 {
+            vec![arc(
+                &self.center,
+                self.radius,
+                self.start_angle,
+                self.sweep * fraction,
+            )]
+        } else {
+            vec![]
+        }
+    }
+
+    fn fill_key(&self, fraction: f32) -> i64 {
+        // Pixels along the arc.
+        (fraction * self.sweep.abs().to_radians() * self.radius).round() as i64
+    }
+}
+
+fn threshold_color(thresholds: &[(f32, Color)], value: f32, color: Color) -> Color {
+    thresholds
+        .iter()
+        .filter(|(at, _)| value >= *at)
+        .max_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, color)| *color)
+        .unwrap_or(color)
+}
+
+/// The value limited to the range, which may run in either direction.
+fn clamp_to(range: (f32, f32), value: f32) -> f32 {
+    let (start, end) = range;
+    value.clamp(start.min(end), start.max(end))
+}
+
+fn fraction(range: (f32, f32), value: f32) -> f32 {
+    let (start, end) = range;
+    if end == start {
+        return 0.0;
+    }
+    ((value - start) / (end - start)).clamp(0.0, 1.0)
+}
+
+/// What is currently drawn.
+#[derive(Default)]
+struct Drawn {
+    value: f32,
+    fill: Option<((i64, Color), Group)>,
+    label: Option<(String, VisualToken)>,
+}
+
+/// The part shared by the bar and the gauge.
+struct Meter {
+    overlay: Overlay,
+    group: Group,
+    /// The track, kept so it stays drawn.
+    _track: Option<Group>,
+    font: Option<PreparedFont>,
+    drawn: Mutex<Drawn>,
+}
+
+impl Meter {
+    fn new<S: Shape>(
+        overlay: &Overlay,
+        shape: &S,
+        background: Color,
+        label: Option<&Label>,
+        font: Option<&PreparedFont>,
+    ) -> Result<Meter, Error> {
+        if label.is_some() && font.is_none() {
+            return Err("a label needs a font".into());
+        }
+        let group = overlay.create_group()?;
+        let track = if background != Color::TRANSPARENT {
+            let track = draw_geometries(overlay, &shape.track(), background, shape.stroke_width())?;
+            group.add_group(&track)?;
+            Some(track)
+        } else {
+            None
+        };
+        Ok(Meter {
+            overlay: overlay.clone(),
+            group,
+            _track: track,
+            font: font.cloned(),
+            drawn: Default::default(),
+        })
+    }
+
+    /// Redraw the fill and the label if they changed.
+    fn update<S: Shape>(
+        &self,
+        shape: &S,
+        value: f32,
+        fraction: f32,
+        color: Color,
+        label: Option<&Label>,
+    ) -> Result<(), Error> {
+        self.overlay.transaction(|tx| {
+            let mut drawn = self.drawn.lock();
+            drawn.value = value;
+            let key = (shape.fill_key(fraction), color);
+            if drawn.fill.as_ref().is_none_or(|(k, _)| *k != key) {
+                let fill = draw_geometries(tx, &shape.fill(fraction), color, shape.stroke_width())?;
+                self.group.add_group(&fill)?;
+                drawn.fill = Some((key, fill));
+            }
+            if let (Some(label), Some(font)) = (label, &self.font) {
+                let text = label.format(value, fraction);
+                if drawn.label.as_ref().is_none_or(|(t, _)| *t != text) {
+                    let token = tx.draw_text(&text, &label.layout, &label.color, font)?;
+                    self.group.add(&token)?;
+                    drawn.label = Some((text, token));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn value(&self) -> f32 {
+        let _guard = self.overlay.overlay.lock();
+        self.drawn.lock().value
+    }
+}
+
+fn draw_geometries(
+    overlay: &Overlay,
+    geometries: &[crate::DrawGeometry],
+    color: Color,
+    width: f32,
+) -> Result<Group, Error> {
+    let group = overlay.create_group()?;
+    let stroke = Stroke { color, width };
+    let style = LineStyle::default();
+    for geometry in geometries {
+        group.add(&overlay.draw_geometry(geometry, &stroke, &style)?)?;
+    }
+    Ok(group)
+}
+
+/// A bar showing a value within a range, optionally split into segments. Removed when dropped.
+///
+/// Geometry isn't drawn on X11 yet, so only the label shows there.
+pub struct ProgressBar {
+    style: ProgressBarStyle,
+    meter: Meter,
+}
+
+impl std::fmt::Debug for ProgressBar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "ProgressBar {}", self.value())
+    }
+}
+
+impl ProgressBar {
+    /// Create the bar at the start of its range, the font is only needed if the style has a label.
+    pub fn new(
+        overlay: &Overlay,
+        style: &ProgressBarStyle,
+        font: Option<&PreparedFont>,
+    ) -> Result<ProgressBar, Error> {
+        let meter = Meter::new(overlay, style, style.background, style.label.as_ref(), font)?;
+        let bar = ProgressBar {
+            style: style.clone(),
+            meter,
+        };
+        bar.set_value(style.range.0)?;
+        Ok(bar)
+    }
+
+    /// Show a new value, it is clamped to the range. Only the parts that change are redrawn.
+    pub fn set_value(&self, value: f32) -> Result<(), Error> {
+        let style = &self.style;
+        let value = clamp_to(style.range, value);
+        let fraction = fraction(style.range, value);
+        let color = threshold_color(&style.thresholds, value, style.color);
+        self.meter
+            .update(style, value, fraction, color, style.label.as_ref())
+    }
+
+    pub fn value(&self) -> f32 {
+        self.meter.value()
+    }
+
+    /// The group holding the bar's visuals.
+    pub fn group(&self) -> &Group {
+        &self.meter.group
+    }
+}
+
+/// An arc showing a value within a range, like a speedometer. Removed when dropped.
+///
+/// Geometry isn't drawn on X11 yet, so only the label shows there.
+pub struct Gauge {
+    style: GaugeStyle,
+    meter: Meter,
+}
+
+impl std::fmt::Debug for Gauge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(f, "Gauge {}", self.value())
+    }
+}
+
+impl Gauge {
+    /// Create the gauge at the start of its range, the font is only needed if the style has a label.
+    pub fn new(
+        overlay: &Overlay,
+        style: &GaugeStyle,
+        font: Option<&PreparedFont>,
+    ) -> Result<Gauge, Error> {
+        let meter = Meter::new(overlay, style, style.background, style.label.as_ref(), font)?;
+        let gauge = Gauge {
+            style: style.clone(),
+            meter,
+        };
+        gauge.set_value(style.range.0)?;
+        Ok(gauge)
+    }
+
+    /// Show a new value, it is clamped to the range. Only the parts that change are redrawn.
+    pub fn set_value(&self, value: f32) -> Result<(), Error> {
+        let style = &self.style;
+        let value = clamp_to(style.range, value);
+        let fraction = fraction(style.range, value);
+        let color = threshold_color(&style.thresholds, value, style.color);
+        self.meter
+            .update(style, value, fraction, color, style.label.as_ref())
+    }
+
+    pub fn value(&self) -> f32 {
+        self.meter.value()
+    }
+
+    /// The group holding the gauge's visuals.
+    pub fn group(&self) -> &Group {
+        &self.meter.group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_bar_segments() {
+        let style = ProgressBarStyle {
+            area: Rect::from(0.0, 0.0).sized(100.0, 10.0),
+            segments: 5,
+            gap: 5.0,
+            ..Default::default()
+        };
+        let spans = style.segment_spans();
+        assert_eq!(spans.len(), 5);
+        assert!((spans[1].0 - 0.21).abs() < 1e-4);
+        assert!((spans[4].1 - 1.0).abs() < 1e-4);
+        // A segment is lit while any part of it is filled.
+        assert_eq!(style.fill(0.0).len(), 0);
+        assert_eq!(style.fill(0.01).len(), 1);
+        assert_eq!(style.fill(0.4).len(), 2);
+        assert_eq!(style.fill(1.0).len(), 5);
+        assert_eq!(style.fill_key(0.3), style.fill_key(0.35));
+    }
+
+    #[test]
+    fn test_meter_values() {
+        assert_eq!(fraction((0.0, 200.0), 50.0), 0.25);
+        assert_eq!(fraction((100.0, 0.0), 25.0), 0.75);
+        assert_eq!(fraction((0.0, 1.0), 3.0), 1.0);
+        assert_eq!(clamp_to((0.0, 1.0), 3.0), 1.0);
+        assert_eq!(clamp_to((100.0, 0.0), -5.0), 0.0);
+        assert_eq!(clamp_to((100.0, 0.0), 25.0), 25.0);
+        let thresholds = [(0.5, Color::rgb(255, 255, 0)), (0.8, Color::rgb(255, 0, 0))];
+        assert_eq!(
+            threshold_color(&thresholds, 0.2, Color::WHITE),
+            Color::WHITE
+        );
+        assert_eq!(
+            threshold_color(&thresholds, 0.9, Color::WHITE),
+            Color::rgb(255, 0, 0)
+        );
+        let label = Label {
+            text: "{value} hp ({percent}%)".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(label.format(75.0, 0.75), "75 hp (75%)");
+    }
+}
@@ -0,0 +1,61 @@
+//! Ready made elements built from the overlay's drawing primitives, they keep themselves up to date.
+//!
+//! Every widget draws into its own [`Group`](crate::Group), which can be used to move, hide or fade it as a whole.
+
+mod countdown;
+pub use countdown::{Countdown, CountdownStyle, Indicator};
+mod graph;
+pub use graph::{Graph, GraphStyle, SeriesStyle, StatsLabel};
+mod meter;
+pub use meter::{Gauge, GaugeStyle, Label, Orientation, ProgressBar, ProgressBarStyle};
+
+use crate::{CircleDirection, DrawGeometry, GeometryElement, Point};
+
+// Lock order: a widget's mutexes holding drawn visuals are only locked inside a transaction on the overlay, or with
+// the overlay lock held, never the other way around. The scheduler runs widget updates on its own thread while
+// callers draw from theirs, taking the overlay lock first on both keeps them from deadlocking.
+
+/// A single open line from `start` to `end`.
+fn segment(start: &Point, end: &Point) -> DrawGeometry {
+    DrawGeometry {
+        elements: vec![
+            GeometryElement::Start {
+                start: *start,
+                filled: false,
+            },
+            GeometryElement::Line(*end),
+            GeometryElement::End { closed: false },
+        ],
+    }
+}
+
+/// An open circular arc, angles are in degrees clockwise from the top of the circle.
+fn arc(center: &Point, radius: f32, start: f32, sweep: f32) -> DrawGeometry {
+    let at = |degrees: f32| {
+        let radians = (degrees - 90.0).to_radians();
+        Point::new(
+            center.x + radius * radians.cos(),
+            center.y + radius * radians.sin(),
+        )
+    };
+    let mut elements = vec![GeometryElement::Start {
+        start: at(start),
+        filled: false,
+    }];
+    // Pieces of at most a quarter circle, so the small arc is always the right one.
+    let pieces = (sweep.abs() / 90.0).ceil().max(1.0) as usize;
+    for i in 1..=pieces {
+        elements.push(GeometryElement::Arc {
+            end_point: at(start + sweep * i as f32 / pieces as f32),
+            radius,
+            angle: 0.0,
+            direction: if sweep >= 0.0 {
+                CircleDirection::ClockWise
+            } else {
+                CircleDirection::CounterClockWise
+            },
+        });
+    }
+    elements.push(GeometryElement::End { closed: false });
+    DrawGeometry { elements }
+}
@@ -27,23 +27,129 @@ use windows::{
 */
 
 use crate::{
-    CapStyle, CircleDirection, Color, DashStyle, DrawGeometry, GeometryElement, LineJoin,
-    LineStyle, OverlayConfig, Point, Rect, Stroke, TextAlignment, TextProperties,
+    CapStyle, CircleDirection, Color, DashStyle, DrawGeometry, DrawTextureOptions, GeometryElement,
+    ImageFormat, Interpolation, LineJoin, LineStyle, Monitor, OverlayConfig, PixelFormat, Point,
+    Rect, Stroke, TextAlignment, TextProperties, Transform2D,
 };
 
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+
+use crate::tree::{Appearance, Tree};
 
 // This is helpful; https://learn.microsoft.com/en-us/windows/win32/directcomp/basic-concepts
 
+struct TextureInner {
+    bitmap: ID2D1Bitmap1,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    /// The overlay the texture was created by, its visuals are redrawn when the texture is updated.
+    owner: OnceLock<crate::WeakOverlay>,
+}
+// The bitmap belongs to a device created in Direct2D's multi threaded mode.
+unsafe impl Send for TextureInner {}
+unsafe impl Sync for TextureInner {}
+
+/// A texture, backed by a Direct2D bitmap that can be drawn on any surface of the overlay's device.
 #[derive(Clone)]
 pub struct ImageTexture {
-    image: Arc<IWICFormatConverter>,
+    inner: Arc<TextureInner>,
 }
 impl std::fmt::Debug for ImageTexture {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "ImageTexture {:?}", &self)
+        write!(
+            f,
+            "ImageTexture {}x{} {:?}",
+            self.inner.width, self.inner.height, self.inner.bitmap
+        )
     }
 }
+impl ImageTexture {
+    fn new(bitmap: ID2D1Bitmap1, width: u32, height: u32, format: PixelFormat) -> Self {
+        Self {
+            inner: Arc::new(TextureInner {
+                bitmap,
+                width,
+                height,
+                format,
+                owner: OnceLock::new(),
+            }),
+        }
+    }
+    pub fn width(&self) -> u32 {
+        self.inner.width
+    }
+    pub fn height(&self) -> u32 {
+        self.inner.height
+    }
+    /// Replace the pixels, `data` must be of the size and format the texture was created with.
+    ///
+    /// Visuals already drawn with the texture are redrawn to show the new contents.
+    pub fn update(&self, data: &[u8]) -> std::result::Result<(), crate::Error> {
+        let inner = &self.inner;
+        let pixels = inner
+            .format
+            .to_premultiplied_bgra(inner.width, inner.height, data)?;
+        let owner = inner.owner.get().and_then(|o| o.upgrade());
+        let guard = owner.as_ref().map(|o| o.overlay.lock());
+        unsafe {
+            inner
+                .bitmap
+                .CopyFromMemory(None, pixels.as_ptr() as _, inner.width * 4)?;
+        }
+        if let Some(guard) = guard {
+            guard.borrow_mut().refresh_texture(self)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn set_owner(&self, overlay: crate::WeakOverlay) {
+        let _ = self.inner.owner.set(overlay);
+    }
+    pub fn downgrade(&self) -> WeakImageTexture {
+        WeakImageTexture {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+}
+
+/// A texture reference that doesn't keep the texture alive.
+#[derive(Clone)]
+pub struct WeakImageTexture {
+    inner: std::sync::Weak<TextureInner>,
+}
+impl WeakImageTexture {
+    pub fn upgrade(&self) -> Option<ImageTexture> {
+        self.inner.upgrade().map(|inner| ImageTexture { inner })
+    }
+}
+
+/// Index of the matrix property of the color matrix effect, from d2d1effects.h.
+const D2D1_COLORMATRIX_PROP_MATRIX: u32 = 0;
+
+/// A color matrix scaling every channel by the tint and the alpha, color matrices work on straight alpha.
+fn tint_matrix(tint: &Color, alpha: f32) -> [f32; 20] {
+    let [r, g, b, a] = tint.to_rgba_f32();
+    #[rustfmt::skip]
+    let matrix = [
+        r, 0.0, 0.0, 0.0,
+        0.0, g, 0.0, 0.0,
+        0.0, 0.0, b, 0.0,
+        0.0, 0.0, 0.0, a * alpha,
+        0.0, 0.0, 0.0, 0.0,
+    ];
+    matrix
+}
+
+const BITMAP_PROPERTIES: D2D1_BITMAP_PROPERTIES1 = D2D1_BITMAP_PROPERTIES1 {
+    pixelFormat: D2D1_PIXEL_FORMAT {
+        format: DXGI_FORMAT_B8G8R8A8_UNORM,
+        alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+    },
+    dpiX: 0.0,
+    dpiY: 0.0,
+    bitmapOptions: D2D1_BITMAP_OPTIONS_NONE,
+    colorContext: std::mem::ManuallyDrop::new(None),
+};
 
 #[derive(Clone)]
 pub struct PreparedFont {
@@ -51,18 +157,14 @@ pub struct PreparedFont {
 }
 impl std::fmt::Debug for PreparedFont {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "PreparedFont {:?}", &self)
+        write!(f, "PreparedFont {:?}", self.text_format)
     }
 }
 
 impl From<Color> for D2D1_COLOR_F {
     fn from(c: Color) -> Self {
-        D2D1_COLOR_F {
-            r: c.r as f32 / 255.0,
-            g: c.g as f32 / 255.0,
-            b: c.b as f32 / 255.0,
-            a: c.a as f32 / 255.0,
-        }
+        let [r, g, b, a] = c.to_rgba_f32();
+        D2D1_COLOR_F { r, g, b, a }
     }
 }
 impl From<Point> for D2D_POINT_2F {
@@ -82,6 +184,28 @@ impl From<Rect> for D2D_RECT_F {
     }
 }
 
+impl From<Transform2D> for Matrix3x2 {
+    fn from(t: Transform2D) -> Self {
+        Matrix3x2 {
+            M11: t.m11,
+            M12: t.m12,
+            M21: t.m21,
+            M22: t.m22,
+            M31: t.dx,
+            M32: t.dy,
+        }
+    }
+}
+
+impl From<Interpolation> for D2D1_INTERPOLATION_MODE {
+    fn from(i: Interpolation) -> Self {
+        match i {
+            Interpolation::Nearest => D2D1_INTERPOLATION_MODE_NEAREST_NEIGHBOR,
+            Interpolation::Linear => D2D1_INTERPOLATION_MODE_LINEAR,
+        }
+    }
+}
+
 impl From<CapStyle> for D2D1_CAP_STYLE {
     fn from(c: CapStyle) -> Self {
         match c {
@@ -127,19 +251,17 @@ impl From<CircleDirection> for D2D1_SWEEP_DIRECTION {
 
 // The IDCompositionVisual appears to be a tree, as per;
 // https://microsoft.github.io/windows-docs-rs/doc/windows/Win32/Graphics/DirectComposition/trait.IDCompositionVisual_Impl.html#tymethod.AddVisual
-// pub type IDVisual = IDCompositionVisual2;
-#[derive(Clone, Debug)]
-pub struct IDVisual {
+// The visual tree mirrors the shared tree, the handle is the node in that tree.
+pub use crate::tree::NodeId as IDVisual;
+
+pub const DRAWS_GEOMETRY: bool = true;
+
+/// The composition visual of a node in the visual tree, and the surface holding its content.
+struct NodeVisual {
     visual: IDCompositionVisual2,
-    _surface: IDCompositionSurface,
-}
-impl IDVisual {
-    pub fn visual_surface(visual: IDCompositionVisual2, surface: IDCompositionSurface) -> Self {
-        Self {
-            _surface: surface,
-            visual,
-        }
-    }
+    surface: Option<IDCompositionSurface>,
+    /// The texture and parts the surface was drawn from, to draw it again when the texture is updated.
+    texture: Option<(ImageTexture, Vec<(Rect, DrawTextureOptions)>)>,
 }
 
 pub struct OverlayImpl {
@@ -147,7 +269,13 @@ pub struct OverlayImpl {
     device: Option<ID3D11Device>,
     desktop: Option<IDCompositionDesktopDevice>,
     target: Option<IDCompositionTarget>,
-    root_visual: Option<IDCompositionVisual2>,
+    /// The root of the tree holds the root visual of the composition target.
+    tree: Option<Tree<NodeVisual>>,
+    /// Number of open batches, nothing is committed while this is non zero.
+    batch: usize,
+    /// Set if a commit was skipped because of an open batch.
+    pending_commit: bool,
+    resource_dc: Option<ID2D1DeviceContext>,
 }
 // Is this legal?
 unsafe impl Send for OverlayImpl {}
@@ -170,7 +298,10 @@ impl OverlayImpl {
             device: None,
             desktop: None,
             target: None,
-            root_visual: None,
+            tree: None,
+            batch: 0,
+            pending_commit: false,
+            resource_dc: None,
         })
     }
 
@@ -245,6 +376,8 @@ impl OverlayImpl {
             debug_assert!(self.device.is_none());
             let device_3d = create_device_3d()?;
             let device_2d = create_device_2d(&device_3d)?;
+            self.resource_dc =
+                Some(device_2d.CreateDeviceContext(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)?);
             self.device = Some(device_3d);
             let desktop: IDCompositionDesktopDevice = DCompositionCreateDevice2(&device_2d)?;
 
@@ -253,7 +386,11 @@ impl OverlayImpl {
             let target = desktop.CreateTargetForHwnd(self.handle, true)?;
             let root_visual = create_visual(&desktop)?;
             target.SetRoot(&root_visual)?;
-            self.root_visual = Some(root_visual.clone());
+            self.tree = Some(Tree::new(NodeVisual {
+                visual: root_visual,
+                surface: None,
+                texture: None,
+            }));
             self.target = Some(target);
 
             desktop.Commit()?;
@@ -263,23 +400,6 @@ impl OverlayImpl {
         }
     }
 
-    fn create_fullscreen_surface_visual(
-        &mut self,
-    ) -> Result<(IDCompositionSurface, IDCompositionVisual2)> {
-        unsafe {
-            let window_rect = self.desired_window_size()?;
-            let visual = create_visual(self.desktop.as_ref().unwrap())?;
-            visual.SetOffsetX2(window_rect.left as f32)?;
-            visual.SetOffsetY2(window_rect.top as f32)?;
-            let width = window_rect.right - window_rect.left;
-            let height = window_rect.bottom - window_rect.top;
-            let surface =
-                create_surface(self.desktop.as_ref().unwrap(), width as f32, height as f32)?;
-            visual.SetContent(&surface)?;
-            Ok((surface, visual))
-        }
-    }
-
     pub fn draw_geometry(
         &mut self,
         geometry: &DrawGeometry,
@@ -288,16 +408,27 @@ impl OverlayImpl {
     ) -> Result<IDVisual> {
         // Objects used together must be created from the same factory instance.
         unsafe {
-            let (surface, visual) = self.create_fullscreen_surface_visual()?;
-            self.root_visual
-                .as_ref()
-                .unwrap()
-                .AddVisual(&visual, false, None)?;
+            // The surface covers the stroked geometry, miter joins reach at most `miter_limit` half widths out and a
+            // pixel is added for the antialiasing.
+            let bounds = geometry
+                .bounds(stroke.width * line_style.miter_limit.max(1.0))
+                .expand(1.0);
+            let (x, y) = (bounds.min.x.floor(), bounds.min.y.floor());
+            let visual = create_visual(self.desktop.as_ref().unwrap())?;
+            visual.SetOffsetX2(x)?;
+            visual.SetOffsetY2(y)?;
+            let width = (bounds.max.x.ceil() - x).max(1.0);
+            let height = (bounds.max.y.ceil() - y).max(1.0);
+            let surface = create_surface(self.desktop.as_ref().unwrap(), width, height)?;
+            visual.SetContent(&surface)?;
 
             let mut offset = Default::default();
             let dc: ID2D1DeviceContext = surface.BeginDraw(None, &mut offset)?;
 
-            dc.SetTransform(&Matrix3x2::translation(offset.x as f32, offset.y as f32));
+            dc.SetTransform(&Matrix3x2::translation(
+                offset.x as f32 - x,
+                offset.y as f32 - y,
+            ));
             dc.Clear(Some(&D2D1_COLOR_F {
                 r: 1.0,
                 g: 1.0,
@@ -309,6 +440,7 @@ impl OverlayImpl {
             // let sink: ID2D1SimplifiedGeometrySink = path_geom.Open()?.cast()?;
             let sink: ID2D1GeometrySink = path_geom.Open()?.cast()?;
             let mut is_started: bool = false;
+            let mut any_filled = false;
             for el in geometry.elements.iter() {
                 match el {
                     GeometryElement::Start { start, filled } => {
@@ -319,6 +451,7 @@ impl OverlayImpl {
                             ));
                         }
                         is_started = true;
+                        any_filled |= *filled;
                         let start_style = if *filled {
                             D2D1_FIGURE_BEGIN_FILLED
                         } else {
@@ -384,22 +517,26 @@ impl OverlayImpl {
                 miterLimit: line_style.miter_limit,
                 dashStyle: line_style.dash_style.into(),
                 dashOffset: line_style.dash_offset,
-                ..Default::default()
             };
             // println!("stroke_props: {stroke_props:?}");
             let stroke_style = dc.GetFactory()?.CreateStrokeStyle(&stroke_props, None)?;
 
+            if any_filled {
+                // Hollow figures don't contribute to the fill.
+                dc.FillGeometry(&path_geom, &brush, None);
+            }
             dc.DrawGeometry(&path_geom, &brush, strokewidth, &stroke_style);
 
             surface.EndDraw()?;
 
-            self.desktop.as_ref().map(|v| v.Commit()).unwrap()?;
-
-            Ok(IDVisual::visual_surface(visual, surface))
+            self.add_visual(visual, Some(surface))
         }
     }
 
-    pub fn prepare_font(&mut self, properties: &TextProperties) -> Result<PreparedFont> {
+    pub fn prepare_font(
+        &mut self,
+        properties: &TextProperties,
+    ) -> std::result::Result<PreparedFont, crate::Error> {
         unsafe {
             let factory: IDWriteFactory2 = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?;
 
@@ -456,10 +593,6 @@ impl OverlayImpl {
             let visual = create_visual(self.desktop.as_ref().unwrap())?;
             visual.SetOffsetX2(layout.min.x)?;
             visual.SetOffsetY2(layout.min.y)?;
-            self.root_visual
-                .as_ref()
-                .unwrap()
-                .AddVisual(&visual, false, None)?;
             let width = layout.width();
             let height = layout.height();
             let surface = create_surface(self.desktop.as_ref().unwrap(), width, height)?;
@@ -500,17 +633,16 @@ impl OverlayImpl {
             );
 
             surface.EndDraw()?;
-            self.desktop.as_ref().map(|v| v.Commit()).unwrap()?;
-
-            Ok(IDVisual::visual_surface(visual, surface))
+            self.add_visual(visual, Some(surface))
         }
     }
 
-    pub fn load_texture<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<ImageTexture> {
+    pub fn load_texture<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> std::result::Result<ImageTexture, crate::Error> {
         unsafe {
-            // This should create a singleton.
-            let factory: IWICImagingFactory2 =
-                CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)?;
+            let factory = create_imaging_factory()?;
 
             let path: std::path::PathBuf = std::path::PathBuf::from(path.as_ref());
             use std::os::windows::ffi::OsStrExt;
@@ -528,94 +660,410 @@ impl OverlayImpl {
                 GENERIC_READ,
                 WICDecodeMetadataCacheOnDemand,
             )?;
+            Ok(self.texture_from_decoder(&factory, &decoder)?)
+        }
+    }
+
+    pub fn load_texture_from_bytes(
+        &mut self,
+        data: &[u8],
+        _format: ImageFormat,
+    ) -> std::result::Result<ImageTexture, crate::Error> {
+        unsafe {
+            let factory = create_imaging_factory()?;
+            // WIC picks the decoder from the stream contents itself.
+            let stream = factory.CreateStream()?;
+            stream.InitializeFromMemory(data)?;
+            let decoder = factory.CreateDecoderFromStream(
+                &stream,
+                std::ptr::null(),
+                WICDecodeMetadataCacheOnDemand,
+            )?;
+            Ok(self.texture_from_decoder(&factory, &decoder)?)
+        }
+    }
 
+    fn texture_from_decoder(
+        &self,
+        factory: &IWICImagingFactory2,
+        decoder: &IWICBitmapDecoder,
+    ) -> Result<ImageTexture> {
+        unsafe {
             let source = decoder.GetFrame(0)?;
-            let image = factory.CreateFormatConverter()?;
+            let image = convert_to_pbgra(factory, &source)?;
+            let bitmap = self
+                .resource_dc()?
+                .CreateBitmapFromWicBitmap(&image, Some(&BITMAP_PROPERTIES))?;
+            let size = bitmap.GetPixelSize();
+
+            Ok(ImageTexture::new(
+                bitmap,
+                size.width,
+                size.height,
+                PixelFormat::Bgra8Premultiplied,
+            ))
+        }
+    }
 
-            image.Initialize(
-                &source,
-                &GUID_WICPixelFormat32bppPBGRA, // not GUID_WICPixelFormat32bppBGRA; https://stackoverflow.com/a/25009124
-                WICBitmapDitherTypeNone,
-                None,
-                0.0,
-                WICBitmapPaletteTypeMedianCut,
+    pub fn create_texture(
+        &mut self,
+        width: u32,
+        height: u32,
+        data: &[u8],
+        format: PixelFormat,
+    ) -> std::result::Result<ImageTexture, crate::Error> {
+        let pixels = format.to_premultiplied_bgra(width, height, data)?;
+        unsafe {
+            let bitmap = self.resource_dc()?.CreateBitmap(
+                D2D_SIZE_U { width, height },
+                Some(pixels.as_ptr() as _),
+                width * 4,
+                &BITMAP_PROPERTIES,
             )?;
-            let image = Arc::new(image);
-            println!("bitmap good");
-
-            Ok(ImageTexture { image })
+            Ok(ImageTexture::new(bitmap, width, height, format))
         }
     }
+
+    /// Device context used to create resources like bitmaps, these can be drawn to every surface of the device.
+    fn resource_dc(&self) -> Result<&ID2D1DeviceContext> {
+        self.resource_dc
+            .as_ref()
+            .ok_or_else(|| Error::new(ERROR_INVALID_STATE.into(), "device resources not created"))
+    }
     pub fn draw_texture(
         &mut self,
-        position: &Point,
         texture: &ImageTexture,
-        texture_region: &Rect,
-        color: &Color,
-        alpha: f32,
+        parts: &[(Rect, DrawTextureOptions)],
     ) -> Result<IDVisual> {
         unsafe {
+            let bounds = texture_bounds(parts);
+            let (x, y) = (bounds.min.x.floor(), bounds.min.y.floor());
             let visual = create_visual(self.desktop.as_ref().unwrap())?;
-            visual.SetOffsetX2(position.x)?;
-            visual.SetOffsetY2(position.y)?;
-            self.root_visual
-                .as_ref()
-                .unwrap()
-                .AddVisual(&visual, false, None)?;
-            let width = texture_region.width();
-            let height = texture_region.height();
+            visual.SetOffsetX2(x)?;
+            visual.SetOffsetY2(y)?;
+            let width = (bounds.max.x.ceil() - x).max(1.0);
+            let height = (bounds.max.y.ceil() - y).max(1.0);
             let surface = create_surface(self.desktop.as_ref().unwrap(), width, height)?;
             visual.SetContent(&surface)?;
+            render_texture(&surface, &Point::new(x, y), texture, parts)?;
 
-            let mut dc_offset = Default::default();
-            let dc: ID2D1DeviceContext = surface.BeginDraw(None, &mut dc_offset)?;
-
-            dc.Clear(Some(&(*color).into()));
+            let id = self.add_visual(visual, Some(surface))?;
+            if let Some(node) = self.tree_mut()?.get_mut(id) {
+                node.data.texture = Some((texture.clone(), parts.to_vec()));
+            }
+            Ok(id)
+        }
+    }
 
-            let properties = D2D1_BITMAP_PROPERTIES1 {
-                pixelFormat: D2D1_PIXEL_FORMAT {
-                    format: DXGI_FORMAT_B8G8R8A8_UNORM,
-                    alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
-                    // ..Default::default()
-                },
-                ..Default::default()
+    /// Draw the surfaces of the visuals showing `texture` again, after its pixels changed.
+    pub fn refresh_texture(&mut self, texture: &ImageTexture) -> Result<()> {
+        let mut refreshed = false;
+        for node in self.tree()?.nodes() {
+            let (Some(surface), Some((drawn, parts))) = (&node.data.surface, &node.data.texture)
+            else {
+                continue;
             };
-            let bitmap = dc.CreateBitmapFromWicBitmap(&*(*texture).image, Some(&properties))?;
+            if Arc::ptr_eq(&drawn.inner, &texture.inner) {
+                let bounds = texture_bounds(parts);
+                let origin = Point::new(bounds.min.x.floor(), bounds.min.y.floor());
+                unsafe { render_texture(surface, &origin, texture, parts)? };
+                refreshed = true;
+            }
+        }
+        if refreshed {
+            self.commit()?;
+        }
+        Ok(())
+    }
 
-            dc.SetTransform(&Matrix3x2::translation(
-                dc_offset.x as f32,
-                dc_offset.y as f32,
-            ));
+    fn tree(&self) -> Result<&Tree<NodeVisual>> {
+        self.tree
+            .as_ref()
+            .ok_or_else(|| Error::new(ERROR_INVALID_STATE.into(), "device resources not created"))
+    }
 
-            dc.DrawBitmap(
-                &bitmap,
+    fn tree_mut(&mut self) -> Result<&mut Tree<NodeVisual>> {
+        self.tree
+            .as_mut()
+            .ok_or_else(|| Error::new(ERROR_INVALID_STATE.into(), "device resources not created"))
+    }
+
+    fn commit(&mut self) -> Result<()> {
+        if self.batch > 0 {
+            self.pending_commit = true;
+            return Ok(());
+        }
+        unsafe { self.desktop.as_ref().map(|v| v.Commit()).unwrap() }
+    }
+
+    /// Defer commits until the matching [`OverlayImpl::end_batch`], batches can be nested.
+    pub fn begin_batch(&mut self) {
+        self.batch += 1;
+    }
+
+    /// Close a batch, everything changed while the last batch was open is committed at once.
+    pub fn end_batch(&mut self) -> Result<()> {
+        self.batch = self.batch.saturating_sub(1);
+        if self.batch == 0 && std::mem::take(&mut self.pending_commit) {
+            self.commit()?;
+        }
+        Ok(())
+    }
+
+    /// Add a drawn visual on top of the root's children and commit.
+    fn add_visual(
+        &mut self,
+        visual: IDCompositionVisual2,
+        surface: Option<IDCompositionSurface>,
+    ) -> Result<IDVisual> {
+        let node = NodeVisual {
+            visual,
+            surface,
+            texture: None,
+        };
+        let id = self
+            .tree_mut()?
+            .insert(IDVisual::ROOT, node)
+            .expect("the root always exists");
+        self.link(id)?;
+        self.commit()?;
+        Ok(id)
+    }
+
+    /// Add the composition visual of a node to its parent's, at the node's place in the tree's drawing order.
+    fn link(&self, id: IDVisual) -> Result<()> {
+        let tree = self.tree()?;
+        let Some(parent) = tree
+            .get(id)
+            .and_then(|n| n.parent)
+            .and_then(|p| tree.get(p))
+        else {
+            return Ok(());
+        };
+        let visual = &tree.get(id).unwrap().data.visual;
+        let index = parent.children.iter().position(|c| *c == id).unwrap();
+        unsafe {
+            match index.checked_sub(1) {
+                Some(below) => {
+                    let below: &IDCompositionVisual =
+                        &tree.get(parent.children[below]).unwrap().data.visual;
+                    parent.data.visual.AddVisual(visual, true, below)?;
+                }
+                None => parent.data.visual.AddVisual(visual, false, None)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the composition visual of a node from its parent's.
+    fn unlink(&self, id: IDVisual) -> Result<()> {
+        let tree = self.tree()?;
+        let Some(node) = tree.get(id) else {
+            return Ok(());
+        };
+        if let Some(parent) = node.parent.and_then(|p| tree.get(p)) {
+            unsafe {
+                parent.data.visual.RemoveVisual(&node.data.visual)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn node_visual(
+        &self,
+        visual: &IDVisual,
+    ) -> std::result::Result<IDCompositionVisual2, crate::Error> {
+        let node = self
+            .tree()?
+            .get(*visual)
+            .ok_or_else(|| format!("visual {visual:?} does not exist"))?;
+        Ok(node.data.visual.clone())
+    }
+
+    pub fn remove_visual(&mut self, visual: &IDVisual) -> Result<()> {
+        let tree = self.tree_mut()?;
+        let parent = tree.get(*visual).and_then(|n| n.parent);
+        let removed = tree.remove(*visual);
+        let (Some(parent), Some((_, first))) = (parent, removed.first()) else {
+            return Ok(());
+        };
+        unsafe {
+            if let Some(parent) = tree.get(parent) {
+                parent.data.visual.RemoveVisual(&first.data.visual)?;
+            }
+            for (_, node) in removed.iter() {
+                node.data.visual.SetContent(None)?;
+            }
+        }
+        self.commit()
+    }
+
+    pub fn create_group(&mut self) -> Result<IDVisual> {
+        let visual = create_visual(self.desktop.as_ref().unwrap())?;
+        self.add_visual(visual, None)
+    }
+
+    pub fn set_parent(
+        &mut self,
+        visual: &IDVisual,
+        parent: &IDVisual,
+    ) -> std::result::Result<(), crate::Error> {
+        let old = self.tree()?.get(*visual).and_then(|n| n.parent);
+        self.tree_mut()?.set_parent(*visual, *parent)?;
+        let composition_visual = self.node_visual(visual)?;
+        if let Some(old) = old {
+            unsafe {
+                self.node_visual(&old)?.RemoveVisual(&composition_visual)?;
+            }
+        }
+        self.link(*visual)?;
+        Ok(self.commit()?)
+    }
+
+    pub fn set_z_index(
+        &mut self,
+        visual: &IDVisual,
+        z_index: i32,
+    ) -> std::result::Result<(), crate::Error> {
+        self.unlink(*visual)?;
+        self.tree_mut()?.set_z_index(*visual, z_index)?;
+        self.link(*visual)?;
+        Ok(self.commit()?)
+    }
+
+    pub fn set_visible(
+        &mut self,
+        visual: &IDVisual,
+        visible: bool,
+    ) -> std::result::Result<(), crate::Error> {
+        let composition_visual: IDCompositionVisual3 = self.node_visual(visual)?.cast()?;
+        unsafe {
+            composition_visual.SetVisible(visible)?;
+        }
+        if let Some(node) = self.tree_mut()?.get_mut(*visual) {
+            node.visible = visible;
+        }
+        Ok(self.commit()?)
+    }
+
+    pub fn is_visible(&self, visual: &IDVisual) -> bool {
+        self.tree
+            .as_ref()
+            .and_then(|t| t.get(*visual))
+            .is_some_and(|n| n.visible)
+    }
+
+    pub fn appearance(&self, visual: &IDVisual) -> Option<Appearance> {
+        self.tree
+            .as_ref()
+            .and_then(|t| t.get(*visual))
+            .map(|n| n.appearance())
+    }
+
+    pub fn monitors(&self) -> Result<Vec<Monitor>> {
+        unsafe extern "system" fn collect(
+            monitor: HMONITOR,
+            _dc: HDC,
+            _clip: *mut RECT,
+            data: LPARAM,
+        ) -> BOOL {
+            let handles = &mut *(data.0 as *mut Vec<HMONITOR>);
+            handles.push(monitor);
+            TRUE
+        }
+        unsafe {
+            let mut handles: Vec<HMONITOR> = vec![];
+            EnumDisplayMonitors(
                 None,
-                alpha, // alpha
-                D2D1_INTERPOLATION_MODE_LINEAR,
-                Some(&D2D_RECT_F {
-                    left: texture_region.min.x,
-                    top: texture_region.min.y,
-                    right: texture_region.max.x,
-                    bottom: texture_region.max.y,
-                }),
                 None,
-            );
+                Some(collect),
+                LPARAM(&mut handles as *mut _ as isize),
+            )
+            .ok()?;
+            // Monitors are in virtual screen coordinates, the overlay window starts at the primary monitor.
+            let window_rect = self.desired_window_size()?;
+            let mut monitors = vec![];
+            for handle in handles {
+                let mut info = MONITORINFOEXW::default();
+                info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+                GetMonitorInfoW(handle, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO)
+                    .ok()?;
+                let r = info.monitorInfo.rcMonitor;
+                let name_len = info.szDevice.iter().position(|c| *c == 0).unwrap_or(32);
+                monitors.push(Monitor {
+                    name: String::from_utf16_lossy(&info.szDevice[..name_len]),
+                    area: Rect::new(
+                        Point::new(
+                            (r.left - window_rect.left) as f32,
+                            (r.top - window_rect.top) as f32,
+                        ),
+                        Point::new(
+                            (r.right - window_rect.left) as f32,
+                            (r.bottom - window_rect.top) as f32,
+                        ),
+                    ),
+                    primary: info.monitorInfo.dwFlags & MONITORINFOF_PRIMARY != 0,
+                });
+            }
+            Ok(monitors)
+        }
+    }
 
-            surface.EndDraw()?;
-            self.desktop.as_ref().map(|v| v.Commit()).unwrap()?;
+    pub fn set_opacity(
+        &mut self,
+        visual: &IDVisual,
+        opacity: f32,
+    ) -> std::result::Result<(), crate::Error> {
+        let composition_visual: IDCompositionVisual3 = self.node_visual(visual)?.cast()?;
+        unsafe {
+            composition_visual.SetOpacity2(opacity)?;
+        }
+        if let Some(node) = self.tree_mut()?.get_mut(*visual) {
+            node.opacity = opacity;
+        }
+        Ok(self.commit()?)
+    }
 
-            Ok(IDVisual::visual_surface(visual, surface))
+    pub fn set_tint(
+        &mut self,
+        visual: &IDVisual,
+        tint: &Color,
+    ) -> std::result::Result<(), crate::Error> {
+        let composition_visual = self.node_visual(visual)?;
+        unsafe {
+            if *tint == Color::WHITE {
+                composition_visual.SetEffect(None)?;
+            } else {
+                let device: IDCompositionDevice3 = self.desktop.as_ref().unwrap().cast()?;
+                let effect = device.CreateColorMatrixEffect()?;
+                let matrix = D2D_MATRIX_5X4_F {
+                    Anonymous: D2D_MATRIX_5X4_F_0 {
+                        m: tint_matrix(tint, 1.0),
+                    },
+                };
+                effect.SetMatrix(&matrix)?;
+                composition_visual.SetEffect(&effect)?;
+            }
+        }
+        if let Some(node) = self.tree_mut()?.get_mut(*visual) {
+            node.tint = *tint;
         }
+        Ok(self.commit()?)
     }
 
-    pub fn remove_visual(&mut self, visual: &IDVisual) -> Result<()> {
+    pub fn set_transform(
+        &mut self,
+        visual: &IDVisual,
+        transform: &Transform2D,
+    ) -> std::result::Result<(), crate::Error> {
         unsafe {
-            visual.visual.SetContent(None)?;
-            let root_visual = self.root_visual.as_ref().unwrap();
-            root_visual.RemoveVisual(&visual.visual)?;
-            self.desktop.as_ref().map(|v| v.Commit()).unwrap()?;
+            self.node_visual(visual)?
+                .SetTransform2(&(*transform).into())?;
         }
-        Ok(())
+        if let Some(node) = self.tree_mut()?.get_mut(*visual) {
+            node.transform = *transform;
+        }
+        Ok(self.commit()?)
     }
 
     fn paint_handler(&mut self) -> Result<()> {
@@ -738,7 +1186,13 @@ fn create_device_3d() -> Result<ID3D11Device> {
 
 fn create_device_2d(device_3d: &ID3D11Device) -> Result<ID2D1Device> {
     let dxgi: IDXGIDevice3 = device_3d.cast()?;
-    unsafe { D2D1CreateDevice(&dxgi, None) }
+    // Multithreaded, as textures can be updated without holding the overlay's lock.
+    let properties = D2D1_CREATION_PROPERTIES {
+        threadingMode: D2D1_THREADING_MODE_MULTI_THREADED,
+        debugLevel: D2D1_DEBUG_LEVEL_NONE,
+        options: D2D1_DEVICE_CONTEXT_OPTIONS_NONE,
+    };
+    unsafe { D2D1CreateDevice(&dxgi, Some(&properties)) }
 }
 
 fn create_visual(device: &IDCompositionDesktopDevice) -> Result<IDCompositionVisual2> {
@@ -749,6 +1203,70 @@ fn create_visual(device: &IDCompositionDesktopDevice) -> Result<IDCompositionVis
     }
 }
 
+/// The axis aligned bounds of the transformed regions, the surface of a texture visual covers these.
+fn texture_bounds(parts: &[(Rect, DrawTextureOptions)]) -> Rect {
+    parts
+        .iter()
+        .map(|(region, options)| region.transformed_bounds(&options.transform(region)))
+        .reduce(|a, b| a.union(&b))
+        .unwrap_or(Rect::new(Point::ORIGIN, Point::ORIGIN))
+}
+
+/// Draw the parts of a texture onto a surface whose top left corner is at `origin`.
+unsafe fn render_texture(
+    surface: &IDCompositionSurface,
+    origin: &Point,
+    texture: &ImageTexture,
+    parts: &[(Rect, DrawTextureOptions)],
+) -> Result<()> {
+    let mut dc_offset = Default::default();
+    let dc: ID2D1DeviceContext = surface.BeginDraw(None, &mut dc_offset)?;
+
+    dc.Clear(Some(&Color::TRANSPARENT.into()));
+
+    for (region, options) in parts {
+        let transform = options.transform(region).then(&Transform2D::translation(
+            dc_offset.x as f32 - origin.x,
+            dc_offset.y as f32 - origin.y,
+        ));
+        dc.SetTransform(&transform.into());
+
+        if options.background != Color::TRANSPARENT {
+            let brush = dc.CreateSolidColorBrush(&options.background.into(), None)?;
+            dc.FillRectangle(&(*region).into(), &brush);
+        }
+
+        if options.tint == Color::WHITE {
+            dc.DrawBitmap(
+                &texture.inner.bitmap,
+                Some(&(*region).into()),
+                options.alpha,
+                options.interpolation.into(),
+                Some(&(*region).into()),
+                None,
+            );
+        } else {
+            let matrix = tint_matrix(&options.tint, options.alpha);
+            let effect = dc.CreateEffect(&CLSID_D2D1ColorMatrix)?;
+            effect.SetInput(0, &texture.inner.bitmap, true);
+            effect.SetValue(
+                D2D1_COLORMATRIX_PROP_MATRIX,
+                D2D1_PROPERTY_TYPE_MATRIX_5X4,
+                std::slice::from_raw_parts(matrix.as_ptr() as *const u8, size_of_val(&matrix)),
+            )?;
+            dc.DrawImage(
+                &effect.GetOutput()?,
+                Some(&region.min.into()),
+                Some(&(*region).into()),
+                options.interpolation.into(),
+                D2D1_COMPOSITE_MODE_SOURCE_OVER,
+            );
+        }
+    }
+
+    surface.EndDraw()
+}
+
 fn create_surface(
     device: &IDCompositionDesktopDevice,
     width: f32,
@@ -767,6 +1285,29 @@ fn create_surface(
     }
 }
 
+fn create_imaging_factory() -> Result<IWICImagingFactory2> {
+    // This should create a singleton.
+    unsafe { CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER) }
+}
+
+fn convert_to_pbgra<P: Param<IWICBitmapSource>>(
+    factory: &IWICImagingFactory2,
+    source: P,
+) -> Result<IWICFormatConverter> {
+    unsafe {
+        let image = factory.CreateFormatConverter()?;
+        image.Initialize(
+            source,
+            &GUID_WICPixelFormat32bppPBGRA, // not GUID_WICPixelFormat32bppBGRA; https://stackoverflow.com/a/25009124
+            WICBitmapDitherTypeNone,
+            None,
+            0.0,
+            WICBitmapPaletteTypeMedianCut,
+        )?;
+        Ok(image)
+    }
+}
+
 pub fn setup() -> Result<()> {
     unsafe {
         CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;